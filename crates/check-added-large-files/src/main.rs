@@ -37,9 +37,16 @@ fn main() -> Result<()> {
         paths,
     } = Cli::parse();
 
-    let mut ctx = RunContext::default();
-    ctx.dry_run = dry_run;
-    ctx.debug = debug;
+    let ctx = RunContext {
+        dry_run,
+        debug,
+        ..Default::default()
+    };
 
-    precommit_rs::hooks::check_added_large_files::run_with_ctx(&ctx, max_bytes, paths)
+    let outcome =
+        precommit_rs::hooks::check_added_large_files::run_with_ctx(&ctx, max_bytes, paths)?;
+    for message in outcome.diagnostics() {
+        eprintln!("{}", message);
+    }
+    std::process::exit(outcome.exit_code());
 }
@@ -32,9 +32,15 @@ fn main() -> Result<()> {
         paths,
     } = Cli::parse();
 
-    let mut ctx = RunContext::default();
-    ctx.dry_run = dry_run;
-    ctx.debug = debug;
+    let ctx = RunContext {
+        dry_run,
+        debug,
+        ..Default::default()
+    };
 
-    precommit_rs::hooks::pretty_format_json::run_with_ctx(&ctx, paths)
+    let outcome = precommit_rs::hooks::pretty_format_json::run_with_ctx(&ctx, paths)?;
+    for message in outcome.diagnostics() {
+        eprintln!("{}", message);
+    }
+    std::process::exit(outcome.exit_code());
 }
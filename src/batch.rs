@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+/// Bytes left free below the computed limit, as `xargs` does, for anything
+/// the estimate does not account for (auxiliary vector, alignment).
+const HEADROOM: usize = 2048;
+
+/// Smallest command line length assumed to be available.
+const MIN_LENGTH: usize = 4096;
+
+/// Maximum size in bytes of the arguments and environment of a new process.
+#[cfg(unix)]
+pub fn arg_max() -> usize {
+    // SAFETY: sysconf has no preconditions and only reads a system limit
+    let limit = unsafe { libc::sysconf(libc::_SC_ARG_MAX) };
+    if limit > 0 {
+        limit as usize
+    } else {
+        // POSIX minimum
+        4096
+    }
+}
+
+/// Maximum length in characters of a command line passed to CreateProcess.
+#[cfg(not(unix))]
+pub fn arg_max() -> usize {
+    32767
+}
+
+/// Space taken by one argument or environment entry: the string, its NUL
+/// terminator and the pointer to it.
+fn entry_size(len: usize) -> usize {
+    len + 1 + std::mem::size_of::<usize>()
+}
+
+/// Size of the environment a child will inherit, including `extra`
+/// variables set on its command.
+pub fn environment_size(extra: Option<&HashMap<String, String>>) -> usize {
+    let inherited: usize = std::env::vars_os()
+        .map(|(k, v)| entry_size(k.len() + 1 + v.len()))
+        .sum();
+    let extra: usize = extra
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| entry_size(k.len() + 1 + v.len()))
+        .sum();
+    inherited + extra
+}
+
+/// Length available for the arguments of a command run with an environment
+/// of `env_size` bytes.
+pub fn max_command_length(env_size: usize) -> usize {
+    arg_max()
+        .saturating_sub(env_size)
+        .saturating_sub(HEADROOM)
+        .max(MIN_LENGTH)
+}
+
+/// Split `paths` into consecutive batches so that `fixed` (the program and
+/// its leading arguments) plus each batch fits in `max_length` bytes. When
+/// `jobs` is more than one the paths are also spread over at least that
+/// many batches (if there are enough paths) so they can run in parallel.
+///
+/// A path too long to fit with `fixed` on its own still gets its own batch
+/// and is left to fail when the command runs. An empty `paths` gives one
+/// empty batch so the command still runs once.
+pub fn partition<'a>(
+    fixed: &[&OsStr],
+    paths: &'a [PathBuf],
+    max_length: usize,
+    jobs: usize,
+) -> Vec<&'a [PathBuf]> {
+    let fixed_length: usize = fixed.iter().map(|a| entry_size(a.len())).sum();
+    let max_count = paths.len().div_ceil(jobs.max(1)).max(1);
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut length = fixed_length;
+    for (i, path) in paths.iter().enumerate() {
+        let size = entry_size(path.as_os_str().len());
+        if i > start && (length + size > max_length || i - start == max_count) {
+            batches.push(&paths[start..i]);
+            start = i;
+            length = fixed_length;
+        }
+        length += size;
+    }
+    if start < paths.len() || batches.is_empty() {
+        batches.push(&paths[start..]);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(n: usize) -> Vec<PathBuf> {
+        (0..n)
+            .map(|i| PathBuf::from(format!("file{:03}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn batches_stay_under_the_limit_and_keep_order() {
+        let fixed = [OsStr::new("tool"), OsStr::new("--check")];
+        let files = paths(100);
+        let fixed_length = entry_size(4) + entry_size(7);
+        let max = fixed_length + 10 * entry_size(7);
+
+        let batches = partition(&fixed, &files, max, 1);
+        assert_eq!(batches.len(), 10);
+        assert!(batches.iter().all(|b| b.len() == 10));
+        assert_eq!(batches.concat(), files);
+    }
+
+    #[test]
+    fn empty_and_oversized_inputs_still_run() {
+        let fixed = [OsStr::new("tool")];
+        assert_eq!(partition(&fixed, &[], 10, 4), vec![&[] as &[PathBuf]]);
+
+        let files = paths(3);
+        let batches = partition(&fixed, &files, 1, 1);
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches.concat(), files);
+    }
+
+    #[test]
+    fn spreads_paths_over_jobs() {
+        let fixed = [OsStr::new("tool")];
+        let files = paths(10);
+        let sizes: Vec<usize> = partition(&fixed, &files, usize::MAX, 4)
+            .iter()
+            .map(|b| b.len())
+            .collect();
+        assert_eq!(sizes, vec![3, 3, 3, 1]);
+        assert_eq!(partition(&fixed, &files[..2], usize::MAX, 8).len(), 2);
+    }
+
+    #[test]
+    fn limit_accounts_for_the_environment() {
+        assert!(max_command_length(0) > max_command_length(environment_size(None)));
+        assert_eq!(max_command_length(usize::MAX), MIN_LENGTH);
+    }
+}
@@ -18,6 +18,12 @@ pub struct Changelog {
     has_changes: bool,
 }
 
+impl Default for Changelog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Changelog {
     pub fn new() -> Self {
         Changelog {
@@ -56,13 +62,16 @@ impl Changelog {
         self.has_changes
     }
 
-    pub fn write_if_changed(&self) -> Result<()> {
-        eprintln!("Checking for changes to write to changelog");
+    pub fn write_if_changed(&self, debug: bool) -> Result<()> {
         if !self.has_changes {
-            eprintln!("No changes to write to changelog");
+            if debug {
+                eprintln!("No changes to write to changelog");
+            }
             return Ok(());
         }
-        eprintln!("Writing changes to changelog...");
+        if debug {
+            eprintln!("Writing changes to changelog...");
+        }
 
         let now = Local::now();
         let date_str = now.format("%Y-%m-%d %H:%M:%S");
@@ -0,0 +1,252 @@
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Config file of upstream pre-commit, read with upstream's defaults.
+pub const UPSTREAM_CONFIG_FILE: &str = ".pre-commit-config.yaml";
+
+/// Top-level keys precommit-rs acts on.
+pub fn config_keys() -> &'static [String] {
+    static KEYS: OnceLock<Vec<String>> = OnceLock::new();
+    KEYS.get_or_init(|| schema_keys(None, is_upstream_config_key))
+}
+
+/// Keys of an entry in `repos`.
+pub fn repo_keys() -> &'static [String] {
+    static KEYS: OnceLock<Vec<String>> = OnceLock::new();
+    KEYS.get_or_init(|| schema_keys(Some("RepoConfig"), |_| false))
+}
+
+/// Keys of a hook.
+pub fn hook_keys() -> &'static [String] {
+    static KEYS: OnceLock<Vec<String>> = OnceLock::new();
+    KEYS.get_or_init(|| schema_keys(Some("HookConfig"), is_upstream_hook_key))
+}
+
+/// Keys of a hook's `install` block.
+pub fn install_keys() -> &'static [String] {
+    static KEYS: OnceLock<Vec<String>> = OnceLock::new();
+    KEYS.get_or_init(|| schema_keys(Some("InstallConfig"), |_| false))
+}
+
+/// Properties of the config schema, or of its definition `def`, less the
+/// upstream keys it also accepts. The schema is generated from the config
+/// structs, so a new field is known here without further changes.
+fn schema_keys(def: Option<&str>, is_upstream: fn(&str) -> bool) -> Vec<String> {
+    let schema = crate::config::config_schema();
+    let object = match def {
+        Some(def) => &schema["$defs"][def],
+        None => &schema,
+    };
+    let properties = object["properties"]
+        .as_object()
+        .unwrap_or_else(|| panic!("config schema has no properties for {:?}", def));
+    properties
+        .keys()
+        .filter(|key| !is_upstream(key))
+        .cloned()
+        .collect()
+}
+
+fn contains(keys: &[String], key: &str) -> bool {
+    keys.iter().any(|k| k == key)
+}
+
+/// Upstream keys that are accepted without a warning although they change
+/// nothing here: `ci` configures the pre-commit.ci service, the minimum
+/// version is upstream's own and `description` is documentation.
+const INERT_CONFIG_KEYS: &[&str] = &["ci", "minimum_pre_commit_version"];
+const INERT_HOOK_KEYS: &[&str] = &["description", "minimum_pre_commit_version"];
+
+/// Upstream keys precommit-rs does not act on, warned about when present.
+const UNSUPPORTED_CONFIG_KEYS: &[&str] = &["default_language_version"];
+const UNSUPPORTED_HOOK_KEYS: &[&str] = &["alias", "language_version"];
+
+/// Top-level keys of upstream's schema that precommit-rs accepts without
+/// acting on them.
+pub fn upstream_config_keys() -> impl Iterator<Item = &'static str> {
+    INERT_CONFIG_KEYS
+        .iter()
+        .chain(UNSUPPORTED_CONFIG_KEYS)
+        .copied()
+}
+
+/// Hook keys of upstream's schema that precommit-rs accepts without acting
+/// on them.
+pub fn upstream_hook_keys() -> impl Iterator<Item = &'static str> {
+    INERT_HOOK_KEYS.iter().chain(UNSUPPORTED_HOOK_KEYS).copied()
+}
+
+/// Whether `key` is a top-level key of upstream's schema that precommit-rs
+/// accepts without acting on it, as opposed to an unknown key.
+pub fn is_upstream_config_key(key: &str) -> bool {
+    upstream_config_keys().any(|k| k == key)
+}
+
+/// Whether `key` is a hook key of upstream's schema that precommit-rs
+/// accepts without acting on it.
+pub fn is_upstream_hook_key(key: &str) -> bool {
+    upstream_hook_keys().any(|k| k == key)
+}
+
+/// Whether a key is ignored with a warning: neither one of the `known` keys
+/// precommit-rs acts on nor an upstream key that is inert here.
+fn is_ignored(
+    key: &str,
+    known: &[String],
+    is_upstream: fn(&str) -> bool,
+    unsupported: &[&str],
+) -> bool {
+    !contains(known, key) && (!is_upstream(key) || unsupported.contains(&key))
+}
+
+/// Whether `path` names upstream's config file, whose `files` and `exclude`
+/// are regexes.
+pub fn is_upstream_config(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == UPSTREAM_CONFIG_FILE)
+}
+
+fn keys(mapping: &Mapping) -> impl Iterator<Item = &str> {
+    mapping.keys().filter_map(Value::as_str)
+}
+
+fn get<'a>(mapping: &'a Mapping, key: &str) -> Option<&'a Value> {
+    mapping.get(Value::String(key.to_string()))
+}
+
+/// Warnings for everything in a parsed config that precommit-rs ignores:
+/// unknown or unsupported keys at every level, `meta` hooks and hooks from
+/// remote repositories, which are not run.
+pub fn unsupported(doc: &Value) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let Some(top) = doc.as_mapping() else {
+        return warnings;
+    };
+    for key in keys(top) {
+        if is_ignored(
+            key,
+            config_keys(),
+            is_upstream_config_key,
+            UNSUPPORTED_CONFIG_KEYS,
+        ) {
+            warnings.push(format!("unsupported key '{}' is ignored", key));
+        }
+    }
+
+    let repos = get(top, "repos").and_then(Value::as_sequence);
+    for repo in repos.into_iter().flatten().filter_map(Value::as_mapping) {
+        let name = get(repo, "repo").and_then(Value::as_str).unwrap_or("");
+        for key in keys(repo).filter(|key| !contains(repo_keys(), key)) {
+            warnings.push(format!(
+                "unsupported key '{}' in repo '{}' is ignored",
+                key, name
+            ));
+        }
+
+        let hooks = get(repo, "hooks").and_then(Value::as_sequence);
+        for hook in hooks.into_iter().flatten().filter_map(Value::as_mapping) {
+            let id = get(hook, "id").and_then(Value::as_str).unwrap_or("");
+            match name {
+                "local" => {}
+                "meta" => {
+                    warnings.push(format!(
+                        "meta hook '{}' is not supported and will not run",
+                        id
+                    ));
+                    continue;
+                }
+                _ => {
+                    warnings.push(format!(
+                        "hook '{}' from repo '{}' will not run: only hooks of `repo: local` are run",
+                        id, name
+                    ));
+                    continue;
+                }
+            }
+            for key in keys(hook) {
+                if is_ignored(
+                    key,
+                    hook_keys(),
+                    is_upstream_hook_key,
+                    UNSUPPORTED_HOOK_KEYS,
+                ) {
+                    warnings.push(format!(
+                        "unsupported key '{}' in hook '{}' is ignored",
+                        key, id
+                    ));
+                }
+            }
+            let install = get(hook, "install").and_then(Value::as_mapping);
+            for key in install.into_iter().flat_map(keys) {
+                if !contains(install_keys(), key) {
+                    warnings.push(format!(
+                        "unsupported key '{}' in install of hook '{}' is ignored",
+                        key, id
+                    ));
+                }
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_keys_come_from_the_config_structs() {
+        assert!(contains(config_keys(), "default_install_hook_types"));
+        assert!(!contains(config_keys(), "ci"));
+        assert_eq!(repo_keys(), ["hooks", "repo", "rev"]);
+        for key in ["working-dir", "require_serial", "install"] {
+            assert!(contains(hook_keys(), key), "{}", key);
+        }
+        assert!(!contains(hook_keys(), "alias"));
+        assert!(contains(install_keys(), "install_args"));
+    }
+
+    #[test]
+    fn warns_on_everything_that_is_ignored() {
+        let doc: Value = serde_yaml::from_str(
+            "
+minimum_pre_commit_version: '3.0.0'
+default_language_version:
+  python: python3.12
+ci:
+  autofix_prs: true
+exclude: '^vendor/'
+repos:
+  - repo: https://github.com/psf/black
+    rev: 24.1.0
+    hooks:
+      - id: black
+  - repo: meta
+    hooks:
+      - id: check-useless-excludes
+  - repo: local
+    hooks:
+      - id: fmt
+        entry: cargo fmt --
+        language: system
+        description: Format Rust code
+        alias: f
+        install:
+          language: rust
+          features: [x]
+",
+        )
+        .unwrap();
+        assert_eq!(
+            unsupported(&doc),
+            vec![
+                "unsupported key 'default_language_version' is ignored",
+                "hook 'black' from repo 'https://github.com/psf/black' will not run: only hooks of `repo: local` are run",
+                "meta hook 'check-useless-excludes' is not supported and will not run",
+                "unsupported key 'alias' in hook 'fmt' is ignored",
+                "unsupported key 'features' in install of hook 'fmt' is ignored",
+            ]
+        );
+    }
+}
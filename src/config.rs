@@ -1,24 +1,58 @@
-use crate::{lock, RunContext};
+use crate::{
+    batch, compat,
+    file_index::FileIndex,
+    hooks::{HookOutcome, HookRegistry},
+    identify, lock, parallel,
+    pattern::{FileMatcher, PatternSyntax, Patterns},
+    process,
+    summary::{HookResult, HookStatus, RunSummary},
+    validate_config, RunContext,
+};
 use anyhow::{anyhow, Context, Result};
-use glob::Pattern;
-use ignore::WalkBuilder;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::{
     collections::HashMap,
-    env, fs,
+    env,
+    ffi::OsStr,
+    fs,
+    io::Read,
     path::{Path, PathBuf},
     process::Command,
+    time::{Duration, Instant},
 };
 
 const INSTALL_PLACEHOLDER: &str = "{install}";
 const TOOLS_DIR: &str = ".precommit-tools";
 
-#[derive(Debug, Deserialize)]
+/// A `.pre-commit.yaml` config file.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields, transform = allow_upstream_config_keys)]
 pub struct PreCommitConfig {
     repos: Option<Vec<RepoConfig>>,
+    /// Stop running hooks after the first failure
+    fail_fast: Option<bool>,
+    /// Stages for hooks that do not list their own
+    default_stages: Option<Vec<Stage>>,
+    /// Git hooks written by `install` when no --hook-type is given
+    default_install_hook_types: Option<Vec<HookType>>,
+    /// Only files matching this reach any hook
+    files: Option<Patterns>,
+    /// Files no hook should see, in the same syntax as `files`
+    exclude: Option<Patterns>,
+    /// Whether `files` and `exclude` are globs (default) or regexes
+    pattern_syntax: Option<PatternSyntax>,
+    // File the config was loaded from
+    #[serde(skip)]
+    path: PathBuf,
+    // Keys and hooks of the file that have no effect
+    #[serde(skip)]
+    warnings: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// An entry of `repos`; only hooks of `repo: local` are run.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct RepoConfig {
     repo: String,
     rev: Option<String>,
@@ -26,26 +60,54 @@ pub struct RepoConfig {
     hooks: Vec<HookConfig>,
 }
 
-#[derive(Debug, Deserialize)]
+/// A hook: built in when `id` names one, otherwise an external `command`.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields, transform = allow_upstream_hook_keys)]
 pub struct HookConfig {
     id: String,
     name: Option<String>,
     entry: Option<String>,
     language: Option<String>,
-    stages: Option<Vec<String>>,
+    stages: Option<Vec<Stage>>,
     additional_dependencies: Option<Vec<String>>,
     enabled: Option<bool>,
+    /// Stop running later hooks if this one fails
+    fail_fast: Option<bool>,
     args: Option<Vec<String>>,
-    files: Option<String>,
-    // External command to run instead of built-in hook
+    files: Option<Patterns>,
+    /// Files to leave out even if they match `files`
+    exclude: Option<Patterns>,
+    /// Overrides the top-level pattern_syntax for this hook
+    pattern_syntax: Option<PatternSyntax>,
+    /// File type tags (see identify) that must all match
+    types: Option<Vec<String>>,
+    /// File type tags of which at least one must match
+    types_or: Option<Vec<String>>,
+    /// File type tags that must not match
+    exclude_types: Option<Vec<String>>,
+    /// Append the matching files to the command line (default: true)
+    pass_filenames: Option<bool>,
+    /// Run even when no files match
+    always_run: Option<bool>,
+    /// Run the command's file batches one after another, never in parallel
+    require_serial: Option<bool>,
+    /// Show the hook's output even when it passes
+    verbose: Option<bool>,
+    /// File the hook's output is also written to when it is shown
+    log_file: Option<String>,
+    /// Seconds the command may run before it is killed (overrides --timeout)
+    timeout: Option<u64>,
+    /// External command to run instead of built-in hook
     command: Option<String>,
-    // Working directory for external command
+    /// Working directory for external command
     #[serde(rename = "working-dir")]
     working_dir: Option<String>,
     install: Option<InstallConfig>,
 }
 
-#[derive(Debug, Deserialize)]
+/// How precommit-rs installs the tool behind `command: "{install}"`.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct InstallConfig {
     #[serde(default)]
     repo: Option<String>,
@@ -65,32 +127,227 @@ pub struct InstallConfig {
     install_args: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+/// Git hook stage a hook can run in, selected with `run-config --hook-stage`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    #[default]
+    #[serde(alias = "commit")]
+    PreCommit,
+    #[serde(alias = "push")]
+    PrePush,
+    CommitMsg,
+    PrepareCommitMsg,
+    PostCheckout,
+    PostMerge,
+    #[serde(alias = "merge-commit")]
+    PreMergeCommit,
+    PostCommit,
+    PostRewrite,
+    PreRebase,
+    Manual,
+}
+
+/// Git hook script that `install` can write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookType {
+    #[default]
+    PreCommit,
+    PrePush,
+    CommitMsg,
+    PrepareCommitMsg,
+    PostCheckout,
+    PostMerge,
+    PreMergeCommit,
+    PostCommit,
+    PostRewrite,
+    PreRebase,
+}
+
+/// Toolchain used to install an external hook.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum InstallLanguage {
+    #[default]
     Rust,
     Python,
     Node,
     Go,
 }
 
-impl Default for InstallLanguage {
-    fn default() -> Self {
-        InstallLanguage::Rust
+/// Config files looked for when no path is given, in order of preference.
+pub const CONFIG_FILES: &[&str] = &[".pre-commit.yaml", compat::UPSTREAM_CONFIG_FILE];
+
+/// The first of `CONFIG_FILES` present in `dir`.
+pub fn find_config(dir: &Path) -> Option<PathBuf> {
+    CONFIG_FILES.iter().find_map(|name| {
+        let path = dir.join(name);
+        if !path.is_file() {
+            None
+        } else if dir == Path::new(".") {
+            Some(PathBuf::from(name))
+        } else {
+            Some(path)
+        }
+    })
+}
+
+/// `path` itself, or the first of `CONFIG_FILES` in it when it is a directory.
+pub fn resolve_config_path(path: &Path) -> Result<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    find_config(path).ok_or_else(|| {
+        anyhow!(
+            "No {} found in {}",
+            CONFIG_FILES.join(" or "),
+            path.display()
+        )
+    })
+}
+
+/// Line and column (1-based) of a serde_yaml error, if it has them, and its
+/// message without the position serde_yaml writes into it.
+pub fn yaml_error_position(err: &serde_yaml::Error) -> (Option<(usize, usize)>, String) {
+    let message = err.to_string();
+    match err.location() {
+        Some(location) => {
+            let at = format!(" at line {} column {}", location.line(), location.column());
+            (
+                Some((location.line(), location.column())),
+                message.replacen(&at, "", 1),
+            )
+        }
+        None => (None, message),
+    }
+}
+
+/// `path:line:column: message` for a serde_yaml error, or `path: message`
+/// when it has no position.
+pub fn located_yaml_error(path: &Path, err: &serde_yaml::Error) -> String {
+    match yaml_error_position(err) {
+        (Some((line, column)), message) => {
+            format!("{}:{}:{}: {}", path.display(), line, column, message)
+        }
+        (None, message) => format!("{}: {}", path.display(), message),
     }
 }
 
 impl PreCommitConfig {
+    /// Load the config at `path`, or the first of `CONFIG_FILES` in it when
+    /// `path` is a directory.
+    ///
+    /// Upstream's `.pre-commit-config.yaml` is read with upstream's defaults:
+    /// `files` and `exclude` are regexes, and local hooks that are not built
+    /// in run their `entry`. Anything in the file that has no effect here is
+    /// listed by `warnings()`.
     pub fn from_file(path: &Path) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let cfg: PreCommitConfig = serde_yaml::from_str(&content)?;
-        Ok(cfg)
+        let path = resolve_config_path(path)?;
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&path, &content)
+    }
+
+    /// Parse `content`, read from `path`. YAML and type errors are reported
+    /// as `path:line:column: message`.
+    pub fn parse(path: &Path, content: &str) -> Result<Self> {
+        let located = |e| anyhow!("{}", located_yaml_error(path, &e));
+        let cfg: PreCommitConfig = serde_yaml::from_str(content).map_err(located)?;
+        let doc: serde_yaml::Value = serde_yaml::from_str(content).map_err(located)?;
+        Ok(cfg.loaded_from(path, &doc))
+    }
+
+    /// Finish a config deserialized from `doc`, read from `path`: collect
+    /// its warnings and give upstream's file upstream's defaults.
+    pub(crate) fn loaded_from(mut self, path: &Path, doc: &serde_yaml::Value) -> Self {
+        self.warnings = compat::unsupported(doc);
+        if compat::is_upstream_config(path) {
+            self.apply_upstream_defaults();
+        }
+        self.path = path.to_path_buf();
+        self
+    }
+
+    fn apply_upstream_defaults(&mut self) {
+        self.pattern_syntax.get_or_insert(PatternSyntax::Regex);
+        let registry = HookRegistry::builtin();
+        let local = self
+            .repos
+            .iter_mut()
+            .flatten()
+            .filter(|r| r.repo == "local");
+        for hook in local.flat_map(|repo| repo.hooks.iter_mut()) {
+            if hook.command.is_some() || registry.contains(&hook.id) {
+                continue;
+            }
+            let Some(entry) = hook.entry.as_deref() else {
+                continue;
+            };
+            // Split into words like a shell would, as upstream does
+            let Some(words) = shlex::split(entry) else {
+                self.warnings.push(format!(
+                    "entry of hook '{}' has an unterminated quote or escape; the hook will not run",
+                    hook.id
+                ));
+                continue;
+            };
+            let mut words = words.into_iter();
+            let Some(program) = words.next() else {
+                continue;
+            };
+            let mut args: Vec<String> = words.collect();
+            args.extend(hook.args.take().unwrap_or_default());
+            hook.command = Some(program);
+            hook.args = Some(args);
+            match hook.language.as_deref() {
+                None | Some("system") | Some("script") => {}
+                Some(language) => self.warnings.push(format!(
+                    "language '{}' of hook '{}' is not supported; its entry is run from PATH",
+                    language, hook.id
+                )),
+            }
+        }
+    }
+
+    /// Path the config was loaded from by `from_file`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keys and hooks of the config that precommit-rs ignores.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
     }
 
     pub fn repos(&self) -> &[RepoConfig] {
         self.repos.as_deref().unwrap_or(&[])
     }
 
+    pub fn fail_fast(&self) -> bool {
+        self.fail_fast.unwrap_or(false)
+    }
+
+    pub fn default_stages(&self) -> Option<&[Stage]> {
+        self.default_stages.as_deref()
+    }
+
+    pub fn files(&self) -> Option<&Patterns> {
+        self.files.as_ref()
+    }
+
+    pub fn exclude(&self) -> Option<&Patterns> {
+        self.exclude.as_ref()
+    }
+
+    pub fn pattern_syntax(&self) -> PatternSyntax {
+        self.pattern_syntax.unwrap_or_default()
+    }
+
+    pub fn default_install_hook_types(&self) -> Option<&[HookType]> {
+        self.default_install_hook_types.as_deref()
+    }
+
     pub fn local_hooks(&self) -> Vec<(&RepoConfig, &HookConfig)> {
         self.repos
             .as_ref()
@@ -119,62 +376,249 @@ impl RepoConfig {
     }
 }
 
-// Helper function to collect matching files
-fn expand_pattern(pattern: &str) -> Vec<String> {
-    if let (Some(start), Some(end)) = (pattern.find('{'), pattern.find('}')) {
-        if end > start {
-            let before = &pattern[..start];
-            let after = &pattern[end + 1..];
-            let inner = &pattern[start + 1..end];
-            return inner
-                .split(',')
-                .map(|alt| format!("{}{}{}", before, alt.trim(), after))
-                .collect();
+/// Where `run_config` takes the candidate files for each hook from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FileSelection {
+    /// Files staged in the git index, as seen by the installed pre-commit hook.
+    #[default]
+    Staged,
+    /// Every file in the working tree that is not ignored.
+    AllFiles,
+    /// An explicit list of files, relative to the root of the run (see
+    /// [`run_root`]); build one from command line paths with
+    /// [`FileSelection::files`].
+    Files(Vec<PathBuf>),
+}
+
+/// Directory a run is anchored at, as with upstream pre-commit: the root of
+/// the repository containing `cwd`, or `cwd` itself outside a repository.
+/// Hooks run in it and their `files` and `exclude` patterns see paths
+/// relative to it.
+pub fn run_root(cwd: &Path) -> PathBuf {
+    crate::git::repo_root(cwd).unwrap_or_else(|_| cwd.to_path_buf())
+}
+
+impl FileSelection {
+    /// Files a git hook script of the given stage should check: staged files
+    /// for `pre-commit`, `pre-merge-commit` and `manual`, the message file
+    /// (first git argument) for `commit-msg` and `prepare-commit-msg`, the
+    /// files changed by the pushed commits (read from `stdin`) for
+    /// `pre-push`, and nothing for `post-checkout`, `post-merge`,
+    /// `post-commit`, `post-rewrite` and `pre-rebase`.
+    ///
+    /// `stdin` is `None` when it is a terminal, i.e. the stage was given by
+    /// hand rather than by git; `pre-push` then checks every file instead of
+    /// waiting for ref lines that will never come.
+    pub fn for_stage(stage: Stage, hook_args: &[String], stdin: Option<impl Read>) -> Result<Self> {
+        Ok(match stage {
+            Stage::PreCommit | Stage::PreMergeCommit | Stage::Manual => FileSelection::Staged,
+            Stage::CommitMsg | Stage::PrepareCommitMsg => {
+                let message_file = hook_args.first().ok_or_else(|| {
+                    anyhow!("{} requires the commit message file", stage.as_str())
+                })?;
+                FileSelection::Files(vec![PathBuf::from(message_file)])
+            }
+            Stage::PrePush => {
+                let Some(mut stdin) = stdin else {
+                    return Ok(FileSelection::AllFiles);
+                };
+                let remote = hook_args.first().map(String::as_str).unwrap_or("origin");
+                let mut ref_lines = String::new();
+                stdin.read_to_string(&mut ref_lines)?;
+                let cwd = env::current_dir()?;
+                match crate::git::pushed_files(&cwd, remote, &ref_lines)? {
+                    Some(files) => FileSelection::Files(relative_to_root(
+                        &crate::git::repo_root(&cwd)?,
+                        files,
+                    )?),
+                    None => FileSelection::AllFiles,
+                }
+            }
+            Stage::PostCheckout
+            | Stage::PostMerge
+            | Stage::PostCommit
+            | Stage::PostRewrite
+            | Stage::PreRebase => FileSelection::Files(Vec::new()),
+        })
+    }
+
+    /// Files given on the command line (`run-config --files`), resolved
+    /// against `cwd` and made relative to `root`. Each must be an existing
+    /// file or symlink inside `root`.
+    pub fn files(files: &[PathBuf], cwd: &Path, root: &Path) -> Result<Self> {
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("Cannot resolve {}", root.display()))?;
+        let mut resolved = Vec::new();
+        for file in files {
+            let absolute = cwd.join(file);
+            match fs::symlink_metadata(&absolute) {
+                Ok(m) if m.is_file() || m.is_symlink() => {}
+                Ok(_) => return Err(anyhow!("{} is not a file", file.display())),
+                Err(e) => return Err(anyhow!("Cannot check {}: {}", file.display(), e)),
+            }
+            // Resolve the directory but not the file, so a symlink stays one
+            let (Some(parent), Some(name)) = (absolute.parent(), absolute.file_name()) else {
+                return Err(anyhow!("{} is not a file", file.display()));
+            };
+            let path = parent.canonicalize()?.join(name);
+            let relative = path.strip_prefix(&root).map_err(|_| {
+                anyhow!(
+                    "{} is outside the repository {}",
+                    file.display(),
+                    root.display()
+                )
+            })?;
+            resolved.push(relative.to_path_buf());
         }
+        Ok(FileSelection::Files(resolved))
+    }
+
+    /// Files changed between two commits (`run-config --from-ref --to-ref`):
+    /// added, modified and renamed-to paths that still exist in the working
+    /// tree. Deleted files and the old side of renames are left out.
+    pub fn changed_between(from: &str, to: &str) -> Result<Self> {
+        let cwd = env::current_dir()?;
+        let files = crate::git::changed_files(&cwd, from, to)
+            .with_context(|| format!("Failed to list files changed between {} and {}", from, to))?;
+        Ok(FileSelection::Files(relative_to_root(
+            &crate::git::repo_root(&cwd)?,
+            files,
+        )?))
     }
-    vec![pattern.to_string()]
 }
 
-fn collect_files(pattern: Option<&String>) -> Result<Vec<PathBuf>> {
-    if let Some(pattern) = pattern {
-        let mut compiled = Vec::new();
-        for pat in expand_pattern(pattern) {
-            compiled.push(
-                Pattern::new(&pat).map_err(|e| anyhow!("Invalid glob pattern '{}': {}", pat, e))?,
-            );
-        }
+/// Options for a single `run-config` invocation.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub files: FileSelection,
+    /// Only hooks configured for this stage are run.
+    pub stage: Stage,
+    /// Show the output of every hook, not just failing ones.
+    pub verbose: bool,
+    /// Ids of the hooks to run; every hook when empty.
+    pub hook_ids: Vec<String>,
+    /// Ids of hooks reported as skipped instead of run, from `SKIP`.
+    pub skip: Vec<String>,
+}
 
-        let mut paths = Vec::new();
-        let walker = WalkBuilder::new(".")
-            .standard_filters(true)
-            .git_ignore(true)
-            .git_global(true)
-            .git_exclude(true)
-            .build();
-        let root = std::env::current_dir()?;
+/// Hook ids from a comma separated list such as the `SKIP` environment
+/// variable, e.g. `SKIP=check-yaml,clippy`.
+pub fn parse_skip(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect()
+}
 
-        for entry in walker {
-            let entry = entry.map_err(|e| anyhow!("Failed to walk project files: {}", e))?;
-            if !entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+/// Select the hook's files from the run's candidates: those matching the
+/// hook's and the top-level `files` patterns and the hook's types, and
+/// neither its own `exclude` nor the top-level one.
+fn collect_files(
+    ctx: &RunContext,
+    cfg: &PreCommitConfig,
+    h: &HookConfig,
+    index: &FileIndex,
+) -> Result<Vec<PathBuf>> {
+    let syntax = h.pattern_syntax(cfg.pattern_syntax());
+    let compiled = h
+        .files()
+        .map(|p| FileMatcher::new(p.as_slice(), syntax))
+        .transpose()?;
+    let top_level = cfg
+        .files()
+        .map(|p| FileMatcher::new(p.as_slice(), cfg.pattern_syntax()))
+        .transpose()?;
+    let mut excludes = Vec::new();
+    for (scope, pattern, syntax) in [
+        ("top-level", cfg.exclude(), cfg.pattern_syntax()),
+        ("hook", h.exclude(), syntax),
+    ] {
+        if let Some(pattern) = pattern {
+            excludes.push((
+                scope,
+                pattern,
+                FileMatcher::new(pattern.as_slice(), syntax)?,
+            ));
+        }
+    }
+    h.check_types()?;
+
+    let mut paths = Vec::new();
+    for file in index.files() {
+        let relative = file.path();
+        let included = [&top_level, &compiled].iter().all(|matcher| {
+            matcher
+                .as_ref()
+                .is_none_or(|matcher| matcher.is_match(relative))
+        });
+        if !included {
+            continue;
+        }
+        let excluded_by = excludes
+            .iter()
+            .find(|(_, _, compiled)| compiled.is_match(relative));
+        if let Some((scope, pattern, _)) = excluded_by {
+            if ctx.debug {
+                eprintln!(
+                    "Excluding {} from hook {} ({} exclude '{}')",
+                    relative.display(),
+                    h.id(),
+                    scope,
+                    pattern
+                );
+            }
+            continue;
+        }
+        if h.filters_types() {
+            if !h.matches_types(file.tags()?) {
                 continue;
             }
+        } else if file.is_symlink() {
+            // Like upstream's default of `types: [file]`
+            continue;
+        }
+        paths.push(relative.to_path_buf());
+    }
+    Ok(paths)
+}
 
-            let absolute = entry.path();
-            let relative = absolute.strip_prefix(&root).unwrap_or(absolute);
-            let rel_str = relative.to_string_lossy();
-            let abs_str = absolute.to_string_lossy();
+/// Absolute paths reported by git, made relative to the repository `root`,
+/// skipping anything that is not a regular file or symlink in the working
+/// tree (e.g. submodule gitlinks or files deleted since).
+fn relative_to_root(root: &Path, files: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    files
+        .into_iter()
+        .filter(|p| {
+            fs::symlink_metadata(p)
+                .map(|m| m.is_file() || m.is_symlink())
+                .unwrap_or(false)
+        })
+        .map(|p| {
+            p.strip_prefix(root).map(Path::to_path_buf).map_err(|_| {
+                anyhow!(
+                    "{} is outside the repository {}",
+                    p.display(),
+                    root.display()
+                )
+            })
+        })
+        .collect()
+}
 
-            if compiled
-                .iter()
-                .any(|pat| pat.matches(rel_str.as_ref()) || pat.matches(abs_str.as_ref()))
-            {
-                paths.push(absolute.to_path_buf());
-            }
-        }
-        Ok(paths)
-    } else {
-        Ok(vec![PathBuf::from(".")])
+/// Staged files relative to the repository root.
+fn staged_candidates(ctx: &RunContext) -> Result<Vec<PathBuf>> {
+    let cwd = env::current_dir()?;
+    let candidates = relative_to_root(
+        &crate::git::repo_root(&cwd)?,
+        crate::git::staged_files(&cwd)?,
+    )?;
+    if ctx.debug {
+        eprintln!("Staged files: {:?}", candidates);
     }
+    Ok(candidates)
 }
 
 impl HookConfig {
@@ -186,6 +630,11 @@ impl HookConfig {
         self.name.as_deref()
     }
 
+    /// Name shown in the run summary, falling back to the hook id.
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+
     pub fn entry(&self) -> Option<&str> {
         self.entry.as_deref()
     }
@@ -194,10 +643,19 @@ impl HookConfig {
         self.language.as_deref()
     }
 
-    pub fn stages(&self) -> Option<&[String]> {
+    pub fn stages(&self) -> Option<&[Stage]> {
         self.stages.as_deref()
     }
 
+    /// Whether the hook runs at `stage`, using `default_stages` when the hook
+    /// lists none. Hooks without any stages run at every stage.
+    pub fn runs_at(&self, stage: Stage, default_stages: Option<&[Stage]>) -> bool {
+        match self.stages.as_deref().or(default_stages) {
+            Some(stages) => stages.contains(&stage),
+            None => true,
+        }
+    }
+
     pub fn additional_dependencies(&self) -> Option<&[String]> {
         self.additional_dependencies.as_deref()
     }
@@ -206,15 +664,45 @@ impl HookConfig {
         self.enabled.unwrap_or(true)
     }
 
-    pub fn is_builtin(&self) -> bool {
-        matches!(
-            self.id.as_str(),
-            "trailing-whitespace"
-                | "end-of-file-fixer"
-                | "check-yaml"
-                | "pretty-format-json"
-                | "check-added-large-files"
-        )
+    pub fn fail_fast(&self) -> bool {
+        self.fail_fast.unwrap_or(false)
+    }
+
+    pub fn pass_filenames(&self) -> bool {
+        self.pass_filenames.unwrap_or(true)
+    }
+
+    pub fn always_run(&self) -> bool {
+        self.always_run.unwrap_or(false)
+    }
+
+    pub fn require_serial(&self) -> bool {
+        self.require_serial.unwrap_or(false)
+    }
+
+    /// Threads the hook's file batches may use: one for `require_serial`.
+    pub fn jobs(&self, ctx: &RunContext) -> usize {
+        if self.require_serial() {
+            1
+        } else {
+            ctx.jobs
+        }
+    }
+
+    pub fn verbose(&self) -> bool {
+        self.verbose.unwrap_or(false)
+    }
+
+    pub fn log_file(&self) -> Option<&str> {
+        self.log_file.as_deref()
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_secs)
+    }
+
+    pub fn is_builtin(&self, registry: &HookRegistry) -> bool {
+        registry.contains(&self.id)
     }
 
     pub fn command(&self) -> Option<&str> {
@@ -233,8 +721,69 @@ impl HookConfig {
         self.args.as_deref()
     }
 
-    pub fn files(&self) -> Option<&str> {
-        self.files.as_deref()
+    pub fn types(&self) -> &[String] {
+        self.types.as_deref().unwrap_or_default()
+    }
+
+    pub fn types_or(&self) -> &[String] {
+        self.types_or.as_deref().unwrap_or_default()
+    }
+
+    pub fn exclude_types(&self) -> &[String] {
+        self.exclude_types.as_deref().unwrap_or_default()
+    }
+
+    /// Whether any of `types`, `types_or` or `exclude_types` is set.
+    pub fn filters_types(&self) -> bool {
+        !(self.types().is_empty() && self.types_or().is_empty() && self.exclude_types().is_empty())
+    }
+
+    /// Fail on type tags that look like misspellings of ones file
+    /// identification produces. Other unknown tags (upstream knows many
+    /// more) are allowed and simply match no file.
+    pub fn check_types(&self) -> Result<()> {
+        let known = identify::known_tags();
+        for (field, tags) in [
+            ("types", self.types()),
+            ("types_or", self.types_or()),
+            ("exclude_types", self.exclude_types()),
+        ] {
+            for tag in tags.iter().filter(|t| !identify::is_known_tag(t)) {
+                if let Some(candidate) = validate_config::suggestion(tag, &known) {
+                    anyhow::bail!(
+                        "Unknown file type '{}' in {} of hook '{}' (did you mean '{}'?)",
+                        tag,
+                        field,
+                        self.id,
+                        candidate
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a file with `tags` has every tag in `types`, at least one in
+    /// `types_or` (if any are listed) and none in `exclude_types`.
+    pub fn matches_types(&self, tags: &identify::Tags) -> bool {
+        let has = |tag: &String| tags.contains(tag.as_str());
+        self.types().iter().all(has)
+            && (self.types_or().is_empty() || self.types_or().iter().any(has))
+            && !self.exclude_types().iter().any(has)
+    }
+
+    /// Syntax of this hook's `files` and `exclude`, falling back to the
+    /// config-wide `default`.
+    pub fn pattern_syntax(&self, default: PatternSyntax) -> PatternSyntax {
+        self.pattern_syntax.unwrap_or(default)
+    }
+
+    pub fn exclude(&self) -> Option<&Patterns> {
+        self.exclude.as_ref()
+    }
+
+    pub fn files(&self) -> Option<&Patterns> {
+        self.files.as_ref()
     }
 }
 
@@ -293,6 +842,47 @@ impl InstallConfig {
     }
 }
 
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::PreCommit => "pre-commit",
+            Stage::PrePush => "pre-push",
+            Stage::CommitMsg => "commit-msg",
+            Stage::PrepareCommitMsg => "prepare-commit-msg",
+            Stage::PostCheckout => "post-checkout",
+            Stage::PostMerge => "post-merge",
+            Stage::PreMergeCommit => "pre-merge-commit",
+            Stage::PostCommit => "post-commit",
+            Stage::PostRewrite => "post-rewrite",
+            Stage::PreRebase => "pre-rebase",
+            Stage::Manual => "manual",
+        }
+    }
+}
+
+impl HookType {
+    /// File name of the script under the hooks directory, which is also the
+    /// stage passed to `run-config --hook-stage`.
+    pub fn as_str(self) -> &'static str {
+        self.stage().as_str()
+    }
+
+    pub fn stage(self) -> Stage {
+        match self {
+            HookType::PreCommit => Stage::PreCommit,
+            HookType::PrePush => Stage::PrePush,
+            HookType::CommitMsg => Stage::CommitMsg,
+            HookType::PrepareCommitMsg => Stage::PrepareCommitMsg,
+            HookType::PostCheckout => Stage::PostCheckout,
+            HookType::PostMerge => Stage::PostMerge,
+            HookType::PreMergeCommit => Stage::PreMergeCommit,
+            HookType::PostCommit => Stage::PostCommit,
+            HookType::PostRewrite => Stage::PostRewrite,
+            HookType::PreRebase => Stage::PreRebase,
+        }
+    }
+}
+
 impl InstallLanguage {
     fn as_str(self) -> &'static str {
         match self {
@@ -304,162 +894,366 @@ impl InstallLanguage {
     }
 }
 
+/// Split the files so each command line fits in `max_length`, and over
+/// `jobs` batches when there are enough files. A serial hook still gets its
+/// files in batches, run one after another.
+fn file_batches<'a>(
+    h: &HookConfig,
+    cmd: &Path,
+    paths: &'a [PathBuf],
+    max_length: usize,
+    jobs: usize,
+) -> Vec<&'a [PathBuf]> {
+    if !h.pass_filenames() {
+        return vec![&[]];
+    }
+    let mut fixed = vec![cmd.as_os_str()];
+    fixed.extend(h.args.as_deref().unwrap_or_default().iter().map(OsStr::new));
+    batch::partition(&fixed, paths, max_length, jobs)
+}
+
 // Helper function to run external commands
 fn run_external_command(
     ctx: &RunContext,
     h: &HookConfig,
     cmd: &Path,
     paths: &[PathBuf],
-) -> Result<()> {
+) -> Result<(HookOutcome, String)> {
     if ctx.debug {
         eprintln!("Running external command for {}: {}", h.id, cmd.display());
     }
 
-    let mut command = Command::new(cmd);
+    let args = h.args.as_deref().unwrap_or_default();
+    let env = h.install.as_ref().and_then(|i| i.env());
 
-    // Add any configured arguments
-    if let Some(args) = &h.args {
-        command.args(args);
+    let jobs = h.jobs(ctx);
+    let max_length = batch::max_command_length(batch::environment_size(env));
+    let batches = file_batches(h, cmd, paths, max_length, jobs);
+    if ctx.debug && batches.len() > 1 {
+        eprintln!(
+            "Running {} in {} batches of files on up to {} jobs",
+            h.id,
+            batches.len(),
+            jobs
+        );
     }
 
-    // Add paths as arguments (common pattern for external tools)
-    command.args(paths.iter().map(|p| p.as_os_str()));
+    // One deadline for the whole hook, however its files are batched
+    let timeout = h.timeout().or(ctx.timeout);
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
 
-    // Change working directory if specified
-    if let Some(dir) = &h.working_dir {
-        command.current_dir(dir);
-    }
+    let results = parallel::map_ordered(jobs, &batches, |batch| {
+        let mut command = Command::new(cmd);
 
-    // Run the command
-    if let Some(env) = h.install.as_ref().and_then(|i| i.env()) {
-        command.envs(env.iter().map(|(k, v)| (k, v)));
-    }
+        // Add any configured arguments
+        command.args(args);
 
-    if ctx.debug {
-        eprintln!("Running command: {:?}", command);
-    }
+        // Add paths as arguments (common pattern for external tools)
+        command.args(batch.iter().map(|p| p.as_os_str()));
 
-    let status = command.status().map_err(|e| {
-        anyhow!(
-            "Failed to execute external command '{}': {}",
-            cmd.display(),
-            e
-        )
-    })?;
+        // Change working directory if specified
+        if let Some(dir) = &h.working_dir {
+            command.current_dir(dir);
+        }
 
-    if !status.success() {
-        return Err(anyhow!(
-            "External command '{}' failed with status: {}",
-            cmd.display(),
-            status
-        ));
+        if let Some(env) = env {
+            command.envs(env.iter());
+        }
+
+        if ctx.debug {
+            eprintln!("Running command: {:?}", command);
+        }
+
+        // Run the command, capturing output so batches do not interleave
+        process::output(&mut command, deadline)
+    });
+
+    // Combine the batches in order
+    let mut timed_out = false;
+    let mut failure = None;
+    let mut output = Vec::new();
+    for result in results {
+        let result = result.map_err(|e| {
+            anyhow!(
+                "Failed to execute external command '{}': {}",
+                cmd.display(),
+                e
+            )
+        })?;
+        output.extend_from_slice(&result.stdout);
+        output.extend_from_slice(&result.stderr);
+        match result.status {
+            None => timed_out = true,
+            Some(status) if !status.success() && failure.is_none() => failure = Some(status),
+            Some(_) => {}
+        }
     }
 
-    Ok(())
+    let output = String::from_utf8_lossy(&output).into_owned();
+    if let (true, Some(timeout)) = (timed_out, timeout) {
+        ctx.changelog.lock().unwrap().record_change(
+            &h.id,
+            &format!(
+                "Timed out after {:?}: killed external command {}",
+                timeout,
+                cmd.display()
+            ),
+        );
+        return Ok((HookOutcome::TimedOut(timeout), output));
+    }
+    let Some(status) = failure else {
+        return Ok((HookOutcome::Passed, output));
+    };
+
+    let message = format!(
+        "External command '{}' failed with status: {}",
+        cmd.display(),
+        status
+    );
+    Ok((HookOutcome::Failed(vec![message]), output))
 }
 
-// Main function to run the hooks from config
-pub fn run_config(ctx: &RunContext, cfg: &PreCommitConfig) -> Result<()> {
+/// Content hash of each of `paths`, `None` for files that cannot be read.
+fn snapshot(ctx: &RunContext, paths: &[PathBuf]) -> Vec<Option<String>> {
+    parallel::map_ordered(ctx.jobs, paths, |path| lock::sha256_file(path).ok())
+}
+
+/// Run the hooks from config. The current directory must be the root of the
+/// run (see [`run_root`]), which candidate files are relative to.
+pub fn run_config(
+    ctx: &RunContext,
+    cfg: &PreCommitConfig,
+    opts: &RunOptions,
+) -> Result<RunSummary> {
     let hooks = cfg.local_hooks();
     if hooks.is_empty() {
         return Err(anyhow!("No local hooks configured"));
     }
+    if let Some(id) = opts
+        .hook_ids
+        .iter()
+        .find(|id| !hooks.iter().any(|(_, h)| h.id() == id.as_str()))
+    {
+        return Err(anyhow!("No hook with id '{}' in the config", id));
+    }
+
+    // Enumerate the candidate files once; every hook filters this list
+    let cwd = Path::new(".");
+    let (index, stash) = match &opts.files {
+        FileSelection::Staged => {
+            let candidates = staged_candidates(ctx)?;
+            // Hooks must see exactly what is about to be committed, so park
+            // unstaged edits in a patch until they have finished.
+            let stash = crate::git::stash_unstaged(&env::current_dir()?)?;
+            (FileIndex::from_paths(cwd, candidates)?, Some(stash))
+        }
+        FileSelection::AllFiles => (FileIndex::walk(cwd)?, None),
+        FileSelection::Files(files) => (FileIndex::from_paths(cwd, files.clone())?, None),
+    };
+    if ctx.debug {
+        eprintln!("Indexed {} candidate files", index.len());
+    }
+
+    let mut summary = RunSummary::new();
+    let result = run_hooks(ctx, cfg, &hooks, opts, &index, &mut summary);
+
+    // A failed restore is reported with the hook results rather than in
+    // place of them
+    match stash.map(|stash| stash.restore()) {
+        None | Some(Ok(crate::git::Unstash::Applied)) => {}
+        Some(Ok(crate::git::Unstash::FixesRolledBack)) => {
+            ctx.changelog.lock().unwrap().record_change(
+                "run-config",
+                "Unstaged changes conflicted with hook fixes; fixes were rolled back",
+            );
+            summary.add_error(
+                "Stashed changes conflicted with hook auto-fixes; rolled back the fixes and restored your unstaged changes",
+            );
+        }
+        Some(Err(e)) => summary.add_error(&format!("{:#}", e)),
+    }
+    result?;
+
+    // Write changelog if there were any changes
+    ctx.changelog.lock().unwrap().write_if_changed(ctx.debug)?;
 
+    Ok(summary)
+}
+
+fn run_hooks(
+    ctx: &RunContext,
+    cfg: &PreCommitConfig,
+    hooks: &[(&RepoConfig, &HookConfig)],
+    opts: &RunOptions,
+    index: &FileIndex,
+    summary: &mut RunSummary,
+) -> Result<()> {
     for (_, h) in hooks {
+        if !opts.hook_ids.is_empty() && !opts.hook_ids.iter().any(|id| id == h.id()) {
+            continue;
+        }
         let enabled = h.enabled.unwrap_or(true);
         if !enabled {
             continue;
         }
 
+        if !h.runs_at(opts.stage, cfg.default_stages()) {
+            if ctx.debug {
+                eprintln!(
+                    "Skipping hook {}: not configured for stage {}",
+                    h.id(),
+                    opts.stage.as_str()
+                );
+            }
+            continue;
+        }
+
+        if opts.skip.iter().any(|id| id == h.id()) {
+            if ctx.debug {
+                eprintln!("Skipping hook {}: listed in SKIP", h.id());
+            }
+            summary.push(HookResult::new(
+                h.id(),
+                h.display_name(),
+                HookStatus::Skipped,
+            ));
+            continue;
+        }
+
         // Build list of matching files
-        let paths = collect_files(h.files.as_ref())?;
+        let paths = collect_files(ctx, cfg, h, index)?;
 
-        if paths.is_empty() {
+        let (outcome, output) = if paths.is_empty() && !h.always_run() {
             if ctx.debug {
                 eprintln!("Skipping hook {}: no matching files", h.id());
             }
-            continue;
+            (HookOutcome::Skipped, String::new())
+        } else {
+            run_hook(ctx, h, paths)
+                .unwrap_or_else(|e| (HookOutcome::Failed(vec![format!("{:#}", e)]), String::new()))
+        };
+
+        let mut result = HookResult::from_outcome(h.id(), h.display_name(), outcome);
+        result.output = output;
+        result.verbose = opts.verbose || h.verbose();
+        if let Some(log_file) = h.log_file() {
+            if result.shows_output() {
+                fs::write(log_file, &result.output).with_context(|| {
+                    format!("Failed to write output of hook {} to {}", h.id(), log_file)
+                })?;
+            }
         }
+        let failed = result.status.is_failure();
+        summary.push(result);
 
-        // Record files being checked in changelog
-        for path in &paths {
-            ctx.changelog
-                .lock()
-                .unwrap()
-                .record_file_checked(&h.id, path);
+        if failed && (cfg.fail_fast() || h.fail_fast()) {
+            if ctx.debug {
+                eprintln!("Stopping after hook {} failed (fail_fast)", h.id());
+            }
+            break;
         }
+    }
 
-        if let Some(cmd) = h.command() {
-            let exec_path = if h.command_is_install() {
-                if ctx.debug {
-                    eprintln!("Ensuring hook '{}' is installed before execution", h.id);
-                }
-                ensure_installed(ctx, h)?
-            } else {
-                PathBuf::from(cmd)
-            };
+    Ok(())
+}
 
+/// Run a single enabled hook against its matching `paths`, returning its
+/// outcome and the output it printed.
+fn run_hook(
+    ctx: &RunContext,
+    h: &HookConfig,
+    paths: Vec<PathBuf>,
+) -> Result<(HookOutcome, String)> {
+    // Record files being checked in changelog
+    for path in &paths {
+        ctx.changelog
+            .lock()
+            .unwrap()
+            .record_file_checked(&h.id, path);
+    }
+
+    if let Some(cmd) = h.command() {
+        let exec_path = if h.command_is_install() {
             if ctx.debug {
-                eprintln!(
-                    "Recording change in changelog (external command {} -> {})",
-                    h.id,
-                    exec_path.display()
-                );
+                eprintln!("Ensuring hook '{}' is installed before execution", h.id);
             }
-            ctx.changelog.lock().unwrap().record_change(
-                &h.id,
-                &format!("Ran external command: {}", exec_path.display()),
-            );
-            run_external_command(ctx, h, &exec_path, &paths)?;
+            ensure_installed(ctx, h)?
         } else {
-            // Handle built-in hooks
-            match h.id.as_str() {
-                "trailing-whitespace" => {
-                    if ctx.debug {
-                        eprintln!("Running trailing-whitespace from config");
-                    }
-                    crate::hooks::trailing_whitespace::run_with_ctx(ctx, paths)?;
-                }
-                "end-of-file-fixer" => {
-                    if ctx.debug {
-                        eprintln!("Running end-of-file-fixer from config");
-                    }
-                    crate::hooks::end_of_file::run_with_ctx(ctx, paths)?;
-                }
-                "check-yaml" => {
-                    if ctx.debug {
-                        eprintln!("Running check-yaml from config");
-                    }
-                    crate::hooks::check_yaml::run_with_ctx(ctx, paths)?;
-                }
-                "pretty-format-json" => {
-                    if ctx.debug {
-                        eprintln!("Running pretty-format-json from config");
-                    }
-                    crate::hooks::pretty_format_json::run_with_ctx(ctx, paths)?;
-                }
-                "check-added-large-files" => {
-                    if ctx.debug {
-                        eprintln!("Running check-added-large-files from config");
-                    }
-                    let max_bytes = if let Some(args) = &h.args {
-                        args.get(0).and_then(|s| s.parse::<u64>().ok())
-                    } else {
-                        None
-                    };
-                    crate::hooks::check_added_large_files::run_with_ctx(ctx, max_bytes, paths)?;
-                }
-                _ => {
-                    eprintln!("Unknown hook id in config: {}", h.id);
-                }
-            }
+            PathBuf::from(cmd)
+        };
+
+        if ctx.debug {
+            eprintln!(
+                "Recording change in changelog (external command {} -> {})",
+                h.id,
+                exec_path.display()
+            );
         }
+        ctx.changelog.lock().unwrap().record_change(
+            &h.id,
+            &format!("Ran external command: {}", exec_path.display()),
+        );
+        // Formatters such as `prettier --write` fix files and still exit 0,
+        // so compare contents to notice their changes
+        let before = snapshot(ctx, &paths);
+        let (outcome, output) = run_external_command(ctx, h, &exec_path, &paths)?;
+        let after = snapshot(ctx, &paths);
+        let modified: Vec<PathBuf> = paths
+            .iter()
+            .zip(before.iter().zip(&after))
+            .filter(|(_, (before, after))| before != after)
+            .map(|(path, _)| path.clone())
+            .collect();
+        if modified.is_empty() {
+            return Ok((outcome, output));
+        }
+
+        let mut changelog = ctx.changelog.lock().unwrap();
+        for path in &modified {
+            changelog.record_file_modified(&h.id, path);
+        }
+        let outcome = match outcome {
+            HookOutcome::Failed(mut messages) => {
+                messages.push("files were modified by this hook".to_string());
+                messages.extend(modified.iter().map(|p| p.display().to_string()));
+                HookOutcome::Failed(messages)
+            }
+            HookOutcome::TimedOut(timeout) => HookOutcome::TimedOut(timeout),
+            _ => HookOutcome::Modified(modified),
+        };
+        return Ok((outcome, output));
     }
 
-    // Write changelog if there were any changes
-    ctx.changelog.lock().unwrap().write_if_changed()?;
+    // Handle built-in hooks
+    let Some(hook) = ctx.registry.get(&h.id) else {
+        let message = format!("Unknown hook id in config: {}", h.id);
+        return Ok((HookOutcome::Failed(vec![message]), String::new()));
+    };
+    if ctx.debug {
+        eprintln!("Running {} from config", hook.id());
+    }
+    let args = h.args().unwrap_or_default();
+    hook.check_args(args)?;
+    Ok(builtin_output(hook.run(ctx, args, paths)?))
+}
 
-    Ok(())
+/// Move a built-in hook's problems and fixed files into its output, as an
+/// external hook would print them, so `verbose` and `log_file` apply alike.
+fn builtin_output(outcome: HookOutcome) -> (HookOutcome, String) {
+    match outcome {
+        HookOutcome::Failed(messages) => {
+            let output = messages.iter().map(|m| format!("{}\n", m)).collect();
+            (HookOutcome::Failed(Vec::new()), output)
+        }
+        HookOutcome::Modified(files) => {
+            let output = files
+                .iter()
+                .map(|f| format!("Fixing {}\n", f.display()))
+                .collect();
+            (HookOutcome::Modified(Vec::new()), output)
+        }
+        outcome => (outcome, String::new()),
+    }
 }
 
 pub fn ensure_installed(ctx: &RunContext, hook: &HookConfig) -> Result<PathBuf> {
@@ -775,6 +1569,31 @@ fn run_and_check(mut cmd: Command, ctx: &RunContext, label: &str) -> Result<()>
     Ok(())
 }
 
+/// JSON Schema of the config file, generated from `PreCommitConfig` and the
+/// types it contains, for editors to validate and complete configs with.
+/// Unknown keys are rejected, as `validate-config` does, except for the
+/// upstream keys it accepts too.
+pub fn config_schema() -> serde_json::Value {
+    schemars::schema_for!(PreCommitConfig).to_value()
+}
+
+fn allow_upstream_config_keys(schema: &mut schemars::Schema) {
+    allow_keys(schema, compat::upstream_config_keys());
+}
+
+fn allow_upstream_hook_keys(schema: &mut schemars::Schema) {
+    allow_keys(schema, compat::upstream_hook_keys());
+}
+
+/// Add `keys` to the properties of an object schema, accepting any value.
+fn allow_keys(schema: &mut schemars::Schema, keys: impl Iterator<Item = &'static str>) {
+    if let Some(serde_json::Value::Object(properties)) = schema.get_mut("properties") {
+        for key in keys {
+            properties.insert(key.to_string(), serde_json::Value::Bool(true));
+        }
+    }
+}
+
 // Write a default config file with examples
 pub fn write_default_config(path: &std::path::Path) -> Result<()> {
     let lines = [
@@ -786,6 +1605,10 @@ pub fn write_default_config(path: &std::path::Path) -> Result<()> {
         "# For external tools, precommit-rs manages installation automatically.",
         "# Python hooks use the `uv` CLI (https://docs.astral.sh/uv/) to create per-hook virtual environments.",
         "# Ensure `uv`, `npm`, `cargo`, and `go` are available on PATH before running the respective external hooks.",
+        "#",
+        "# Every enabled hook runs even if an earlier one fails; set `fail_fast: true`",
+        "# here (or on a single hook) to stop at the first failure instead.",
+        "fail_fast: false",
         "repos:",
         "  - repo: local",
         "    hooks:",
@@ -871,3 +1694,859 @@ pub fn write_default_config(path: &std::path::Path) -> Result<()> {
     std::fs::write(path, sample)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn index(candidates: &[PathBuf]) -> FileIndex {
+        FileIndex::from_paths(Path::new("."), candidates.to_vec()).unwrap()
+    }
+
+    fn run_on(yaml: &str, candidates: &[PathBuf]) -> RunSummary {
+        run_at(yaml, Stage::PreCommit, candidates)
+    }
+
+    fn run_at(yaml: &str, stage: Stage, candidates: &[PathBuf]) -> RunSummary {
+        let opts = RunOptions {
+            stage,
+            ..Default::default()
+        };
+        run_with(yaml, &opts, candidates)
+    }
+
+    fn run_with(yaml: &str, opts: &RunOptions, candidates: &[PathBuf]) -> RunSummary {
+        let cfg: PreCommitConfig = serde_yaml::from_str(yaml).unwrap();
+        let ctx = RunContext::default();
+        let mut summary = RunSummary::new();
+        run_hooks(
+            &ctx,
+            &cfg,
+            &cfg.local_hooks(),
+            opts,
+            &index(candidates),
+            &mut summary,
+        )
+        .unwrap();
+        summary
+    }
+
+    const TWO_HOOKS: &str = "
+repos:
+  - repo: local
+    hooks:
+      - id: check-yaml
+        name: Check YAML
+      - id: end-of-file-fixer
+";
+
+    #[test]
+    fn runs_every_hook_after_a_failure() {
+        let dir = tempdir().unwrap();
+        let bad = dir.path().join("bad.yaml");
+        fs::write(&bad, "invalid: [yaml: }").unwrap();
+
+        let summary = run_on(TWO_HOOKS, &[bad]);
+        let statuses: Vec<_> = summary.results().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![HookStatus::Failed, HookStatus::Modified]);
+        assert_eq!(summary.results()[0].name, "Check YAML");
+        assert_eq!(summary.results()[1].name, "end-of-file-fixer");
+        assert!(!summary.is_success());
+    }
+
+    #[test]
+    fn skip_and_hook_selection() {
+        let dir = tempdir().unwrap();
+        let bad = dir.path().join("bad.yaml");
+        fs::write(&bad, "invalid: [yaml: }").unwrap();
+        let candidates = std::slice::from_ref(&bad);
+
+        let opts = RunOptions {
+            skip: parse_skip(" check-yaml,,other "),
+            ..Default::default()
+        };
+        assert_eq!(opts.skip, vec!["check-yaml", "other"]);
+        let summary = run_with(TWO_HOOKS, &opts, candidates);
+        let results = summary.results();
+        assert_eq!(results[0].status, HookStatus::Skipped);
+        assert!(results[0].note.is_none());
+        assert_eq!(results[1].status, HookStatus::Modified);
+
+        let opts = RunOptions {
+            hook_ids: vec!["check-yaml".to_string()],
+            ..Default::default()
+        };
+        let summary = run_with(TWO_HOOKS, &opts, candidates);
+        assert_eq!(summary.results().len(), 1);
+        assert_eq!(summary.results()[0].id, "check-yaml");
+    }
+
+    #[test]
+    fn fail_fast_stops_after_first_failure() {
+        let dir = tempdir().unwrap();
+        let bad = dir.path().join("bad.yaml");
+        fs::write(&bad, "invalid: [yaml: }").unwrap();
+
+        let summary = run_on(
+            &format!("fail_fast: true\n{}", TWO_HOOKS),
+            std::slice::from_ref(&bad),
+        );
+        assert_eq!(summary.results().len(), 1);
+
+        let per_hook = TWO_HOOKS.replace(
+            "        name: Check YAML\n",
+            "        name: Check YAML\n        fail_fast: true\n",
+        );
+        let summary = run_on(&per_hook, &[bad]);
+        assert_eq!(summary.results().len(), 1);
+    }
+
+    #[test]
+    fn hooks_run_only_at_their_stages() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "ok\n").unwrap();
+        let yaml = "
+default_stages: [pre-commit, pre-push]
+repos:
+  - repo: local
+    hooks:
+      - id: trailing-whitespace
+      - id: end-of-file-fixer
+        stages: [manual]
+      - id: check-yaml
+        stages: [commit-msg]
+";
+        let ids = |summary: RunSummary| -> Vec<String> {
+            summary.results().iter().map(|r| r.id.clone()).collect()
+        };
+        let files = std::slice::from_ref(&file);
+        assert_eq!(
+            ids(run_at(yaml, Stage::PreCommit, files)),
+            ["trailing-whitespace"]
+        );
+        assert_eq!(
+            ids(run_at(yaml, Stage::PrePush, files)),
+            ["trailing-whitespace"]
+        );
+        assert_eq!(
+            ids(run_at(yaml, Stage::Manual, files)),
+            ["end-of-file-fixer"]
+        );
+        assert_eq!(ids(run_at(yaml, Stage::CommitMsg, files)), ["check-yaml"]);
+        assert!(ids(run_at(yaml, Stage::PostMerge, files)).is_empty());
+    }
+
+    #[test]
+    fn explicit_files_are_made_relative_to_the_root() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("repo");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("src/a.txt"), "a\n").unwrap();
+        fs::write(dir.path().join("outside.txt"), "b\n").unwrap();
+        let cwd = root.join("docs");
+        let select = |files: &[PathBuf]| FileSelection::files(files, &cwd, &root);
+
+        let absolute = root.join("src/a.txt");
+        let parent = PathBuf::from("../src/a.txt");
+        let FileSelection::Files(files) = select(&[absolute, parent]).unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(files, vec![PathBuf::from("src/a.txt"); 2]);
+
+        // Anchored patterns see the same paths whichever way they were given
+        let cfg: PreCommitConfig = serde_yaml::from_str(
+            r"
+pattern_syntax: regex
+repos:
+  - repo: local
+    hooks:
+      - id: check-yaml
+        files: ^src/
+      - id: end-of-file-fixer
+        exclude: ^src/
+",
+        )
+        .unwrap();
+        let index = FileIndex::from_paths(&root, files.clone()).unwrap();
+        let hooks = cfg.local_hooks();
+        let ctx = RunContext::default();
+        assert_eq!(
+            collect_files(&ctx, &cfg, hooks[0].1, &index).unwrap(),
+            files
+        );
+        assert!(collect_files(&ctx, &cfg, hooks[1].1, &index)
+            .unwrap()
+            .is_empty());
+
+        let err = select(&[PathBuf::from("../../outside.txt")]).unwrap_err();
+        assert!(
+            err.to_string().contains("is outside the repository"),
+            "{}",
+            err
+        );
+        let err = select(&[PathBuf::from("typo.rs")]).unwrap_err();
+        assert!(err.to_string().starts_with("Cannot check "), "{}", err);
+        let err = select(&[PathBuf::from("..")]).unwrap_err();
+        assert!(err.to_string().ends_with("is not a file"), "{}", err);
+    }
+
+    #[test]
+    fn git_hook_stages_select_their_files() {
+        let select = |stage, args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+            FileSelection::for_stage(stage, &args, Some(std::io::empty())).unwrap()
+        };
+        assert_eq!(select(Stage::PreCommit, &[]), FileSelection::Staged);
+        assert_eq!(select(Stage::PreMergeCommit, &[]), FileSelection::Staged);
+        assert_eq!(
+            select(Stage::PostRewrite, &["amend"]),
+            FileSelection::Files(vec![])
+        );
+        assert_eq!(
+            select(Stage::CommitMsg, &[".git/COMMIT_EDITMSG"]),
+            FileSelection::Files(vec![PathBuf::from(".git/COMMIT_EDITMSG")])
+        );
+        assert_eq!(
+            select(Stage::PostCheckout, &["a", "b", "1"]),
+            FileSelection::Files(vec![])
+        );
+        assert!(FileSelection::for_stage(Stage::CommitMsg, &[], Some(std::io::empty())).is_err());
+        // Run by hand from a terminal: nothing to read, so check everything
+        assert_eq!(
+            FileSelection::for_stage(Stage::PrePush, &[], None::<std::io::Empty>).unwrap(),
+            FileSelection::AllFiles
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pass_filenames_and_always_run() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "a\n").unwrap();
+
+        // Fails when given any arguments beyond its own
+        let hook = |extra: &str| {
+            format!(
+                "
+repos:
+  - repo: local
+    hooks:
+      - id: no-args
+        command: sh
+        args: ['-c', 'exit $#', 'sh']
+{}",
+                extra
+            )
+        };
+        let status =
+            |yaml: String, candidates: &[PathBuf]| run_on(&yaml, candidates).results()[0].status;
+
+        assert_eq!(
+            status(hook(""), std::slice::from_ref(&file)),
+            HookStatus::Failed
+        );
+        assert_eq!(
+            status(
+                hook("        pass_filenames: false"),
+                std::slice::from_ref(&file)
+            ),
+            HookStatus::Passed
+        );
+        assert_eq!(status(hook(""), &[]), HookStatus::Skipped);
+        assert_eq!(
+            status(hook("        always_run: true"), &[]),
+            HookStatus::Passed
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hooks_that_run_too_long_are_killed() {
+        let yaml = |extra: &str| {
+            format!(
+                "
+repos:
+  - repo: local
+    hooks:
+      - id: hang
+        command: sh
+        args: ['-c', 'echo waiting; sleep 30']
+        always_run: true
+        pass_filenames: false
+{}",
+                extra
+            )
+        };
+        let run = |ctx: &RunContext, yaml: &str| {
+            let cfg: PreCommitConfig = serde_yaml::from_str(yaml).unwrap();
+            let mut summary = RunSummary::new();
+            run_hooks(
+                ctx,
+                &cfg,
+                &cfg.local_hooks(),
+                &RunOptions::default(),
+                &index(&[]),
+                &mut summary,
+            )
+            .unwrap();
+            summary.results()[0].clone()
+        };
+
+        let start = std::time::Instant::now();
+        let ctx = RunContext::default();
+        let result = run(&ctx, &yaml("        timeout: 1"));
+        assert_eq!(result.status, HookStatus::TimedOut);
+        assert_eq!(result.output, "waiting\n");
+        assert!(ctx.changelog.lock().unwrap().has_changes());
+
+        // The global limit applies to hooks without their own
+        let ctx = RunContext {
+            timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        assert_eq!(run(&ctx, &yaml("")).status, HookStatus::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(20));
+    }
+
+    #[test]
+    fn output_is_captured_and_logged_when_shown() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("hook.log");
+        let hook = |script: &str, extra: &str| {
+            format!(
+                "
+repos:
+  - repo: local
+    hooks:
+      - id: talk
+        command: sh
+        args: ['-c', '{}']
+        always_run: true
+        pass_filenames: false
+        log_file: {}
+{}",
+                script,
+                log.display(),
+                extra
+            )
+        };
+
+        let result = run_on(&hook("echo quiet", ""), &[]).results()[0].clone();
+        assert_eq!(result.status, HookStatus::Passed);
+        assert_eq!(result.output, "quiet\n");
+        assert!(!result.shows_output());
+        assert!(!log.exists());
+
+        let result = run_on(&hook("echo loud", "        verbose: true"), &[]).results()[0].clone();
+        assert!(result.shows_output());
+        assert_eq!(fs::read_to_string(&log).unwrap(), "loud\n");
+
+        let result = run_on(&hook("echo broken >&2; exit 1", ""), &[]).results()[0].clone();
+        assert_eq!(result.status, HookStatus::Failed);
+        assert!(result.shows_output());
+        assert_eq!(fs::read_to_string(&log).unwrap(), "broken\n");
+    }
+
+    #[test]
+    fn builtin_hook_messages_are_output() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("hook.log");
+        let bad = dir.path().join("bad.yaml");
+        fs::write(&bad, "invalid: [yaml: }").unwrap();
+        let yaml = format!(
+            "
+repos:
+  - repo: local
+    hooks:
+      - id: check-yaml
+        log_file: {}
+",
+            log.display()
+        );
+
+        let result = run_on(&yaml, std::slice::from_ref(&bad)).results()[0].clone();
+        assert_eq!(result.status, HookStatus::Failed);
+        assert!(result.details.is_empty());
+        assert!(result.shows_output());
+        let logged = fs::read_to_string(&log).unwrap();
+        assert!(logged.contains(&bad.display().to_string()), "{}", logged);
+        assert_eq!(logged, result.output);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn long_file_lists_are_split_into_batches() {
+        let yaml = "
+repos:
+  - repo: local
+    hooks:
+      - id: count
+        command: sh
+        args: ['-c', 'echo $#; exit 1', 'sh']
+";
+        // Well over the usual 2 MiB ARG_MAX in total
+        let name = "x".repeat(120);
+        let files: Vec<PathBuf> = (0..30_000)
+            .map(|i| PathBuf::from(format!("{}/{}", name, i)))
+            .collect();
+
+        let summary = run_on(yaml, &files);
+        let result = &summary.results()[0];
+        assert_eq!(result.status, HookStatus::Failed);
+        assert!(result.details[0].contains("failed with status"));
+        assert_eq!(result.details.len(), 1);
+        let counts: Vec<usize> = result.output.lines().map(|l| l.parse().unwrap()).collect();
+        assert!(counts.len() > 1);
+        assert_eq!(counts.iter().sum::<usize>(), files.len());
+    }
+
+    #[test]
+    fn serial_hooks_are_split_but_not_spread() {
+        let cfg: PreCommitConfig = serde_yaml::from_str(
+            "
+repos:
+  - repo: local
+    hooks:
+      - id: lint
+        command: tool
+        args: [--check]
+        require_serial: true
+",
+        )
+        .unwrap();
+        let (_, hook) = cfg.local_hooks()[0];
+        let ctx = RunContext {
+            jobs: 8,
+            ..Default::default()
+        };
+        assert_eq!(hook.jobs(&ctx), 1);
+
+        let files: Vec<PathBuf> = (0..100)
+            .map(|i| PathBuf::from(format!("file{:03}", i)))
+            .collect();
+        let cmd = Path::new("tool");
+        let batches = file_batches(hook, cmd, &files, 256, hook.jobs(&ctx));
+        assert!(batches.len() > 1);
+        assert_eq!(batches.concat(), files);
+        // With room for every file, one process sees them all
+        let batches = file_batches(hook, cmd, &files, usize::MAX, hook.jobs(&ctx));
+        assert_eq!(batches, vec![&files[..]]);
+    }
+
+    #[test]
+    fn exclude_at_hook_and_top_level() {
+        let dir = tempdir().unwrap();
+        let file = |name: &str| {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "a: 1").unwrap();
+            path
+        };
+        let keep = file("values.yaml");
+        let template = file("charts/app/templates/deploy.yaml");
+        let vendored = file("vendor/lib.yaml");
+        let cfg: PreCommitConfig = serde_yaml::from_str(
+            "
+exclude: '**/vendor/**'
+repos:
+  - repo: local
+    hooks:
+      - id: check-yaml
+        files: '**/*.yaml'
+        exclude: '**/charts/**/templates/**'
+",
+        )
+        .unwrap();
+        let (_, hook) = cfg.local_hooks()[0];
+
+        let files = collect_files(
+            &RunContext::default(),
+            &cfg,
+            hook,
+            &index(&[keep.clone(), template, vendored]),
+        )
+        .unwrap();
+        assert_eq!(files, vec![keep]);
+    }
+
+    #[test]
+    fn exclude_ignores_where_the_checkout_lives() {
+        let dir = tempfile::Builder::new()
+            .prefix("vendor-")
+            .tempdir()
+            .unwrap();
+        fs::create_dir(dir.path().join("vendor")).unwrap();
+        fs::write(dir.path().join("a.txt"), "a \n").unwrap();
+        fs::write(dir.path().join("vendor/b.txt"), "b \n").unwrap();
+        let cfg: PreCommitConfig = serde_yaml::from_str(
+            "
+exclude: '**/vendor*/**'
+repos:
+  - repo: local
+    hooks:
+      - id: trailing-whitespace
+",
+        )
+        .unwrap();
+        let (_, hook) = cfg.local_hooks()[0];
+
+        let index = FileIndex::walk(dir.path()).unwrap();
+        let files = collect_files(&RunContext::default(), &cfg, hook, &index).unwrap();
+        assert_eq!(files, vec![PathBuf::from("a.txt")]);
+    }
+
+    #[test]
+    fn loads_upstream_config_with_upstream_defaults() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(".pre-commit-config.yaml"),
+            r#"
+minimum_pre_commit_version: '3.0.0'
+default_language_version:
+  python: python3
+files: \.(rs|toml)$
+exclude: generated
+repos:
+  - repo: meta
+    hooks:
+      - id: identity
+  - repo: local
+    hooks:
+      - id: fmt
+        name: rustfmt
+        entry: cargo fmt --
+        language: system
+        args: [--check]
+      - id: check-yaml
+        entry: check-yaml
+        language: system
+        stages: [pre-commit, pre-merge-commit, merge-commit]
+      - id: echo
+        entry: sh -c 'echo "$@"' --
+        language: system
+        stages: [post-commit, post-rewrite, pre-rebase]
+      - id: broken
+        entry: sh -c 'echo
+        language: system
+default_install_hook_types: [pre-commit, post-commit, pre-merge-commit]
+"#,
+        )
+        .unwrap();
+
+        let cfg = PreCommitConfig::from_file(dir.path()).unwrap();
+        assert_eq!(cfg.path(), dir.path().join(".pre-commit-config.yaml"));
+        assert_eq!(cfg.pattern_syntax(), PatternSyntax::Regex);
+        assert_eq!(cfg.warnings().len(), 3, "{:?}", cfg.warnings());
+        assert!(cfg.warnings()[2].contains("hook 'broken'"));
+
+        let hooks = cfg.local_hooks();
+        let (_, fmt) = hooks[0];
+        assert_eq!(fmt.command(), Some("cargo"));
+        assert_eq!(
+            fmt.args(),
+            Some(&["fmt", "--", "--check"].map(String::from)[..])
+        );
+        // Built-in hooks stay built in
+        let (_, yaml) = hooks[1];
+        assert_eq!(yaml.command(), None);
+        let (_, echo) = hooks[2];
+        assert_eq!(echo.command(), Some("sh"));
+        assert_eq!(
+            echo.args(),
+            Some(&["-c", "echo \"$@\"", "--"].map(String::from)[..])
+        );
+        assert_eq!(hooks[3].1.command(), None);
+
+        let files: Vec<PathBuf> = ["src/main.rs", "Cargo.toml", "src/generated.rs", "a.py"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        let selected = collect_files(&RunContext::default(), &cfg, fmt, &index(&files)).unwrap();
+        assert_eq!(selected, files[..2]);
+
+        // Our own file wins when both exist
+        fs::write(dir.path().join(".pre-commit.yaml"), "repos: []\n").unwrap();
+        let cfg = PreCommitConfig::from_file(dir.path()).unwrap();
+        assert_eq!(cfg.path(), dir.path().join(".pre-commit.yaml"));
+        assert_eq!(cfg.pattern_syntax(), PatternSyntax::Glob);
+    }
+
+    #[test]
+    fn default_config_matches_the_schema() {
+        let validator = jsonschema::validator_for(&config_schema()).unwrap();
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(".pre-commit.yaml");
+        write_default_config(&path).unwrap();
+        let config: serde_json::Value =
+            serde_yaml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&config)
+            .map(|e| e.to_string())
+            .collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let wrong: serde_json::Value = serde_yaml::from_str(
+            "
+repos:
+  - repo: local
+    hooks:
+      - id: x
+        timeout: soon
+        install:
+          language: cobol
+",
+        )
+        .unwrap();
+        assert_eq!(validator.iter_errors(&wrong).count(), 2);
+
+        let typos: serde_json::Value = serde_yaml::from_str(
+            "
+exlude: vendor/**
+minimum_pre_commit_version: '3.0.0'
+repos:
+  - repo: local
+    hooks:
+      - id: check-yaml
+        enable: false
+        alias: yaml
+        install:
+          pakage: x
+",
+        )
+        .unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&typos)
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        for key in ["'exlude'", "'enable'", "pakage"] {
+            assert!(errors.iter().any(|e| e.contains(key)), "{:?}", errors);
+        }
+    }
+
+    #[test]
+    fn regex_pattern_syntax_per_config_and_hook() {
+        let cfg: PreCommitConfig = serde_yaml::from_str(
+            r"
+pattern_syntax: regex
+exclude: ^vendor/
+repos:
+  - repo: local
+    hooks:
+      - id: check-yaml
+        files: \.ya?ml$
+      - id: end-of-file-fixer
+        pattern_syntax: glob
+        files: '**/*.md'
+",
+        )
+        .unwrap();
+        let hooks = cfg.local_hooks();
+        let candidates = [
+            PathBuf::from("a.yml"),
+            PathBuf::from("vendor/b.yaml"),
+            PathBuf::from("docs/c.md"),
+        ];
+        let ctx = RunContext::default();
+        let files = |i: usize| collect_files(&ctx, &cfg, hooks[i].1, &index(&candidates)).unwrap();
+        assert_eq!(files(0), vec![PathBuf::from("a.yml")]);
+        assert_eq!(files(1), vec![PathBuf::from("docs/c.md")]);
+    }
+
+    #[test]
+    fn selects_files_by_type() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, content: &[u8]| {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let script = write("script.sh", b"echo hi\n");
+        let python = write("tool.py", b"print(1)\n");
+        let image = write("logo.png", b"\x89PNG\0");
+        let notes = write("NOTES", b"text\n");
+        let candidates = [script.clone(), python.clone(), image, notes.clone()];
+
+        let select = |yaml: &str| {
+            let cfg: PreCommitConfig = serde_yaml::from_str(yaml).unwrap();
+            let (_, hook) = cfg.local_hooks()[0];
+            collect_files(&RunContext::default(), &cfg, hook, &index(&candidates))
+        };
+        let hook = |filters: &str| {
+            format!(
+                "repos:\n  - repo: local\n    hooks:\n      - id: check-yaml\n{}\n",
+                filters
+            )
+        };
+
+        assert_eq!(
+            select(&hook(
+                "        types: [text]\n        exclude_types: [python]"
+            ))
+            .unwrap(),
+            vec![script.clone(), notes]
+        );
+        assert_eq!(
+            select(&hook("        types_or: [python, shell]")).unwrap(),
+            vec![script, python]
+        );
+        let err = select(&hook("        types: [pyhton]")).unwrap_err();
+        assert!(err.to_string().contains("Unknown file type 'pyhton'"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_only_reach_hooks_that_ask_for_them() {
+        let dir = tempdir().unwrap();
+        crate::git::test_support::init_repo(dir.path());
+        fs::write(dir.path().join("target.txt"), "text \n").unwrap();
+        std::os::unix::fs::symlink("target.txt", dir.path().join("link.txt")).unwrap();
+        crate::git::test_support::git(dir.path(), &["add", "."]);
+
+        let cfg: PreCommitConfig = serde_yaml::from_str(
+            "
+repos:
+  - repo: local
+    hooks:
+      - id: links
+        command: true
+        types: [symlink]
+      - id: trailing-whitespace
+",
+        )
+        .unwrap();
+        let select = |index: &FileIndex| -> Vec<Vec<String>> {
+            cfg.local_hooks()
+                .iter()
+                .map(|(_, hook)| {
+                    collect_files(&RunContext::default(), &cfg, hook, index)
+                        .unwrap()
+                        .iter()
+                        .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                        .collect()
+                })
+                .collect()
+        };
+        let expected = vec![vec!["link.txt"], vec!["target.txt"]];
+
+        let root = crate::git::repo_root(dir.path()).unwrap();
+        let staged =
+            relative_to_root(&root, crate::git::staged_files(dir.path()).unwrap()).unwrap();
+        assert_eq!(staged, [Path::new("link.txt"), Path::new("target.txt")]);
+        let staged = FileIndex::from_paths(&root, staged).unwrap();
+        assert_eq!(select(&staged), expected);
+        assert_eq!(select(&FileIndex::walk(dir.path()).unwrap()), expected);
+
+        // Fixers given a symlink directly leave its target alone
+        let link = dir.path().join("link.txt");
+        let outcome =
+            crate::hooks::trailing_whitespace::run_with_ctx(&RunContext::default(), vec![link])
+                .unwrap();
+        assert_eq!(outcome, crate::hooks::HookOutcome::Skipped);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("target.txt")).unwrap(),
+            "text \n"
+        );
+    }
+
+    /// Eight hooks over a synthetic 50k-file tree share one walk and one
+    /// classification per file. Run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_shared_index_50k_files() {
+        let dir = tempdir().unwrap();
+        let extensions = ["rs", "py", "yaml", "json", "md"];
+        for d in 0..500 {
+            let sub = dir.path().join(format!("pkg{:03}", d));
+            fs::create_dir(&sub).unwrap();
+            for f in 0..100 {
+                let ext = extensions[f % extensions.len()];
+                fs::write(sub.join(format!("f{:03}.{}", f, ext)), "x\n").unwrap();
+            }
+        }
+
+        let cfg: PreCommitConfig = serde_yaml::from_str(
+            "
+repos:
+  - repo: local
+    hooks:
+      - id: a
+        files: '**/*.rs'
+      - id: b
+        files: ['**/*.{py,pyi}', '!pkg00*/**']
+      - id: c
+        types: [yaml]
+      - id: d
+        types_or: [json, markdown]
+      - id: e
+        types: [text]
+        exclude_types: [rust]
+      - id: f
+        exclude: '**/*.md'
+      - id: g
+        files: '**/f00*'
+        types: [non-executable]
+      - id: h
+        files: '**/*.{rs,py}'
+        exclude: 'pkg1*/**'
+",
+        )
+        .unwrap();
+        let ctx = RunContext::default();
+
+        let start = std::time::Instant::now();
+        let index = FileIndex::walk(dir.path()).unwrap();
+        let walked = start.elapsed();
+        assert_eq!(index.len(), 50_000);
+
+        let mut selected = Vec::new();
+        for (_, hook) in cfg.local_hooks() {
+            selected.push(collect_files(&ctx, &cfg, hook, &index).unwrap().len());
+        }
+        let total = start.elapsed();
+        eprintln!(
+            "walk: {:?}, walk + 8 hooks: {:?}, selected: {:?}",
+            walked, total, selected
+        );
+        assert_eq!(selected[0], 10_000);
+        assert_eq!(selected[2], 10_000);
+        assert_eq!(selected[3], 20_000);
+        assert_eq!(selected[4], 40_000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn external_hooks_that_edit_files_are_reported() {
+        let dir = tempdir().unwrap();
+        let clean = dir.path().join("clean.txt");
+        let dirty = dir.path().join("dirty.txt");
+        fs::write(&clean, "fixed\n").unwrap();
+        fs::write(&dirty, "broken\n").unwrap();
+
+        // Rewrites every file and exits 0, like a formatter
+        let yaml = "
+repos:
+  - repo: local
+    hooks:
+      - id: fmt
+        command: sh
+        args: ['-c', 'for f; do echo fixed > \"$f\"; done', 'sh']
+";
+        let summary = run_on(yaml, &[clean, dirty.clone()]);
+        let result = &summary.results()[0];
+        assert_eq!(result.status, HookStatus::Modified);
+        assert_eq!(
+            result.details,
+            vec![
+                "files were modified by this hook".to_string(),
+                dirty.display().to_string()
+            ]
+        );
+        assert!(!summary.is_success());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::identify::{self, Tags};
+use anyhow::{anyhow, Result};
+use ignore::WalkBuilder;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// A candidate file of a run, with its type tags computed at most once.
+#[derive(Debug)]
+pub struct IndexedFile {
+    path: PathBuf,
+    absolute: PathBuf,
+    symlink: bool,
+    tags: OnceLock<std::result::Result<Tags, String>>,
+}
+
+impl IndexedFile {
+    fn new(path: PathBuf, absolute: PathBuf, symlink: bool) -> Self {
+        Self {
+            path,
+            absolute,
+            symlink,
+            tags: OnceLock::new(),
+        }
+    }
+
+    /// Path relative to the index root, as handed to hooks.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn absolute(&self) -> &Path {
+        &self.absolute
+    }
+
+    /// Whether the file is a symlink, which only hooks asking for the
+    /// `symlink` type are given.
+    pub fn is_symlink(&self) -> bool {
+        self.symlink
+    }
+
+    /// Type tags from `identify`, classified on first use and then cached.
+    pub fn tags(&self) -> Result<&Tags> {
+        self.tags
+            .get_or_init(|| {
+                identify::tags_from_path(&self.absolute).map_err(|e| format!("{:#}", e))
+            })
+            .as_ref()
+            .map_err(|e| anyhow!("{}", e))
+    }
+}
+
+/// Every candidate file of a run, enumerated once and shared by all hooks.
+#[derive(Debug, Default)]
+pub struct FileIndex {
+    files: Vec<IndexedFile>,
+}
+
+fn absolute_root(root: &Path) -> Result<PathBuf> {
+    let root = if root.is_absolute() {
+        root.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(root)
+    };
+    Ok(root.canonicalize().unwrap_or(root))
+}
+
+impl FileIndex {
+    /// Index an explicit list of files, given relative to `root`.
+    pub fn from_paths(root: &Path, paths: Vec<PathBuf>) -> Result<Self> {
+        let root = absolute_root(root)?;
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let absolute = root.join(&path);
+                let symlink = fs::symlink_metadata(&absolute).is_ok_and(|m| m.is_symlink());
+                IndexedFile::new(path, absolute, symlink)
+            })
+            .collect();
+        Ok(Self { files })
+    }
+
+    /// Index every file and symlink under `root` that is not hidden or ignored
+    /// by `.gitignore`, the global gitignore or `.git/info/exclude`, in one
+    /// walk. Symlinks are not followed.
+    pub fn walk(root: &Path) -> Result<Self> {
+        let absolute = absolute_root(root)?;
+        let walker = WalkBuilder::new(root)
+            .standard_filters(true)
+            .git_ignore(true)
+            .git_global(true)
+            .git_exclude(true)
+            .build();
+
+        let mut files = Vec::new();
+        for entry in walker {
+            let entry = entry.map_err(|e| anyhow!("Failed to walk project files: {}", e))?;
+            let Some(file_type) = entry.file_type() else {
+                continue;
+            };
+            if !file_type.is_file() && !file_type.is_symlink() {
+                continue;
+            }
+            let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            files.push(IndexedFile::new(
+                path.to_path_buf(),
+                absolute.join(path),
+                file_type.is_symlink(),
+            ));
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Self { files })
+    }
+
+    pub fn files(&self) -> &[IndexedFile] {
+        &self.files
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn walk_skips_hidden_and_ignored_files_and_caches_tags() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("target/out.bin"), b"\0").unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+
+        let index = FileIndex::walk(dir.path()).unwrap();
+        let paths: Vec<&Path> = index.files().iter().map(|f| f.path()).collect();
+        assert_eq!(paths, vec![Path::new("src/main.rs")]);
+
+        let main = &index.files()[0];
+        assert!(main.tags().unwrap().contains("rust"));
+        // Cached: still answers after the file is gone
+        fs::remove_file(dir.path().join("src/main.rs")).unwrap();
+        assert!(main.tags().unwrap().contains("rust"));
+    }
+}
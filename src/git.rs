@@ -0,0 +1,518 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn git_command(dir: &Path) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(dir);
+    cmd
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = git_command(dir)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to execute git {}", args.join(" ")))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+fn split_nul(bytes: &[u8]) -> impl Iterator<Item = String> + '_ {
+    bytes
+        .split(|b| *b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+}
+
+/// Top-level directory of the work tree containing `dir`.
+pub fn repo_root(dir: &Path) -> Result<PathBuf> {
+    let out = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&out).trim()))
+}
+
+fn absolute_git_path(dir: &Path, args: &[&str]) -> Result<PathBuf> {
+    let mut full = vec!["rev-parse", "--path-format=absolute"];
+    full.extend_from_slice(args);
+    let out = run_git(dir, &full)?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&out).trim()))
+}
+
+/// Directory git runs hooks from for the repository containing `dir`. This
+/// follows `core.hooksPath` and is shared by every worktree of a repository;
+/// for a submodule it lives under the superproject's `.git/modules`.
+pub fn hooks_dir(dir: &Path) -> Result<PathBuf> {
+    absolute_git_path(dir, &["--git-path", "hooks"])
+}
+
+/// The `.git` directory shared by all worktrees of the repository.
+pub fn common_dir(dir: &Path) -> Result<PathBuf> {
+    absolute_git_path(dir, &["--git-common-dir"])
+}
+
+/// Value of a git config key, or `None` if it is not set.
+pub fn config_value(dir: &Path, key: &str) -> Result<Option<String>> {
+    let output = git_command(dir)
+        .args(["config", "--get", key])
+        .output()
+        .with_context(|| format!("Failed to execute git config --get {}", key))?;
+    match output.status.code() {
+        Some(0) => Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        )),
+        Some(1) => Ok(None),
+        _ => Err(anyhow!(
+            "git config --get {} failed: {}",
+            key,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+/// Files added, copied, modified or renamed in the index of the repository
+/// containing `dir`, as absolute paths. Deleted paths are never returned.
+pub fn staged_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let root = repo_root(dir)?;
+    let out = run_git(
+        dir,
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "--diff-filter=ACMR",
+            "-z",
+        ],
+    )?;
+    Ok(split_nul(&out).map(|p| root.join(p)).collect())
+}
+
+/// Files added, copied, modified or renamed between `from` and `to` (using
+/// the merge base of the two, like `git diff from...to`), as absolute paths.
+pub fn changed_files(dir: &Path, from: &str, to: &str) -> Result<Vec<PathBuf>> {
+    let root = repo_root(dir)?;
+    let range = format!("{}...{}", from, to);
+    let out = run_git(
+        dir,
+        &[
+            "diff",
+            "--name-only",
+            "--no-ext-diff",
+            "--diff-filter=ACMR",
+            "-z",
+            &range,
+        ],
+    )?;
+    Ok(split_nul(&out).map(|p| root.join(p)).collect())
+}
+
+fn rev_exists(dir: &Path, rev: &str) -> bool {
+    run_git(dir, &["cat-file", "-e", &format!("{}^{{commit}}", rev)]).is_ok()
+}
+
+fn is_zero_sha(sha: &str) -> bool {
+    !sha.is_empty() && sha.bytes().all(|b| b == b'0')
+}
+
+/// Files touched by the commits a pre-push hook is about to push.
+///
+/// `ref_lines` is the hook's stdin (`<local ref> <local sha> <remote ref>
+/// <remote sha>` per line) and `remote` the remote name git passed as the
+/// first argument. Returns `None` when a pushed branch shares no history with
+/// the remote, in which case every file should be checked.
+pub fn pushed_files(dir: &Path, remote: &str, ref_lines: &str) -> Result<Option<Vec<PathBuf>>> {
+    let mut files = Vec::new();
+    for line in ref_lines.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [_, local_sha, _, remote_sha] = fields[..] else {
+            continue;
+        };
+        if is_zero_sha(local_sha) {
+            // Deleting a remote branch: nothing to check
+            continue;
+        }
+
+        let from = if !is_zero_sha(remote_sha) && rev_exists(dir, remote_sha) {
+            remote_sha.to_string()
+        } else {
+            // New branch: start from the first commit the remote does not have
+            let not_remote = format!("--remotes={}", remote);
+            let out = run_git(
+                dir,
+                &[
+                    "rev-list",
+                    local_sha,
+                    "--topo-order",
+                    "--reverse",
+                    "--not",
+                    &not_remote,
+                ],
+            )?;
+            let out = String::from_utf8_lossy(&out);
+            let Some(first) = out.lines().next() else {
+                continue;
+            };
+            if !rev_exists(dir, &format!("{}^", first)) {
+                return Ok(None);
+            }
+            format!("{}^", first)
+        };
+
+        for file in changed_files(dir, &from, local_sha)? {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(Some(files))
+}
+
+/// Unstaged changes to tracked files, saved as a binary patch under the git
+/// directory while hooks run against the index version of the work tree.
+///
+/// The patch is re-applied by `restore`, or on drop if that is never reached
+/// (an error or panic while the hooks ran), so unstaged edits are not left
+/// behind in the patch file.
+#[derive(Debug)]
+pub struct StashedChanges {
+    root: PathBuf,
+    patch: Option<PathBuf>,
+}
+
+/// How unstaged changes were put back after the hooks ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unstash {
+    /// There was nothing to stash, or the patch applied on top of any fixes.
+    Applied,
+    /// The patch conflicted with hook edits; those edits were discarded
+    /// before re-applying the patch.
+    FixesRolledBack,
+}
+
+/// Save unstaged changes of the repository containing `dir` to a patch and
+/// check out the index version of every tracked file.
+pub fn stash_unstaged(dir: &Path) -> Result<StashedChanges> {
+    let root = repo_root(dir)?;
+    let tree = run_git(&root, &["write-tree"])?;
+    let tree = String::from_utf8_lossy(&tree).trim().to_string();
+
+    let output = git_command(&root)
+        .args([
+            "diff-index",
+            "--ignore-submodules",
+            "--binary",
+            "--exit-code",
+            "--no-color",
+            "--no-ext-diff",
+            &tree,
+            "--",
+        ])
+        .output()
+        .context("Failed to execute git diff-index")?;
+    match output.status.code() {
+        Some(0) => return Ok(StashedChanges { root, patch: None }),
+        Some(1) => {}
+        _ => {
+            return Err(anyhow!(
+                "git diff-index failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+
+    let git_dir = run_git(&root, &["rev-parse", "--absolute-git-dir"])?;
+    let git_dir = PathBuf::from(String::from_utf8_lossy(&git_dir).trim());
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let patch = git_dir.join(format!(
+        "precommit-rs-patch-{}-{}",
+        stamp,
+        std::process::id()
+    ));
+    std::fs::write(&patch, &output.stdout)
+        .with_context(|| format!("Failed to write {}", patch.display()))?;
+    eprintln!("Stashed unstaged changes to {}", patch.display());
+
+    checkout_index(&root)?;
+    Ok(StashedChanges {
+        root,
+        patch: Some(patch),
+    })
+}
+
+fn checkout_index(root: &Path) -> Result<()> {
+    run_git(
+        root,
+        &[
+            "-c",
+            "core.autocrlf=false",
+            "-c",
+            "submodule.recurse=0",
+            "checkout",
+            "--",
+            ".",
+        ],
+    )?;
+    Ok(())
+}
+
+fn apply_patch(root: &Path, patch: &Path) -> Result<()> {
+    let patch = patch.to_string_lossy();
+    run_git(
+        root,
+        &[
+            "-c",
+            "core.autocrlf=false",
+            "apply",
+            "--whitespace=nowarn",
+            &patch,
+        ],
+    )?;
+    Ok(())
+}
+
+impl StashedChanges {
+    /// Path of the saved patch, if there were unstaged changes.
+    pub fn patch(&self) -> Option<&Path> {
+        self.patch.as_deref()
+    }
+
+    /// Re-apply the saved patch. If it no longer applies because hooks edited
+    /// the same lines, the hook edits are discarded and the patch is applied
+    /// to the index version instead. The patch file is removed on success.
+    pub fn restore(mut self) -> Result<Unstash> {
+        self.unstash()
+    }
+
+    fn unstash(&mut self) -> Result<Unstash> {
+        let Some(patch) = self.patch.take() else {
+            return Ok(Unstash::Applied);
+        };
+
+        let outcome = if apply_patch(&self.root, &patch).is_ok() {
+            Unstash::Applied
+        } else {
+            checkout_index(&self.root)?;
+            apply_patch(&self.root, &patch).with_context(|| {
+                format!(
+                    "Failed to restore unstaged changes; they are saved in {}",
+                    patch.display()
+                )
+            })?;
+            Unstash::FixesRolledBack
+        };
+
+        let _ = std::fs::remove_file(&patch);
+        Ok(outcome)
+    }
+}
+
+impl Drop for StashedChanges {
+    fn drop(&mut self) {
+        if self.patch.is_none() {
+            return;
+        }
+        match self.unstash() {
+            Ok(Unstash::Applied) => {}
+            Ok(Unstash::FixesRolledBack) => {
+                eprintln!("Restored unstaged changes; hook fixes conflicted and were rolled back")
+            }
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::path::Path;
+    use std::process::Command;
+
+    /// Run git in `dir` with a fixed identity, panicking on failure.
+    pub fn git(dir: &Path, args: &[&str]) -> String {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args([
+                "-c",
+                "commit.gpgsign=false",
+                "-c",
+                "init.defaultBranch=main",
+            ])
+            .args(args)
+            .output()
+            .expect("failed to run git");
+        assert!(
+            out.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&out.stderr)
+        );
+        String::from_utf8_lossy(&out.stdout).into_owned()
+    }
+
+    pub fn init_repo(dir: &Path) {
+        git(dir, &["init", "-q"]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{git, init_repo};
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn staged_files_lists_only_index_changes() {
+        let dir = tempdir().unwrap();
+        init_repo(dir.path());
+        std::fs::write(dir.path().join("committed.txt"), "a\n").unwrap();
+        std::fs::write(dir.path().join("gone.txt"), "a\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "init"]);
+
+        std::fs::write(dir.path().join("staged.txt"), "b\n").unwrap();
+        std::fs::write(dir.path().join("unstaged.txt"), "c\n").unwrap();
+        git(dir.path(), &["add", "staged.txt"]);
+        git(dir.path(), &["rm", "-q", "gone.txt"]);
+
+        let root = repo_root(dir.path()).unwrap();
+        let files = staged_files(dir.path()).unwrap();
+        assert_eq!(files, vec![root.join("staged.txt")]);
+    }
+
+    fn partially_staged_repo() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        init_repo(dir.path());
+        std::fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "init"]);
+        std::fs::write(dir.path().join("a.txt"), "one \n").unwrap();
+        git(dir.path(), &["add", "a.txt"]);
+        std::fs::write(dir.path().join("a.txt"), "one \ntwo\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn stash_checks_out_index_and_restores() {
+        let dir = partially_staged_repo();
+        let file = dir.path().join("a.txt");
+
+        let stash = stash_unstaged(dir.path()).unwrap();
+        let patch = stash.patch().unwrap().to_path_buf();
+        assert!(patch.exists());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "one \n");
+
+        assert_eq!(stash.restore().unwrap(), Unstash::Applied);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "one \ntwo\n");
+        assert!(!patch.exists());
+    }
+
+    #[test]
+    fn conflicting_fixes_are_rolled_back() {
+        let dir = partially_staged_repo();
+        let file = dir.path().join("a.txt");
+
+        let stash = stash_unstaged(dir.path()).unwrap();
+        // Simulate a fixer rewriting the line the unstaged hunk depends on.
+        std::fs::write(&file, "one\n").unwrap();
+
+        assert_eq!(stash.restore().unwrap(), Unstash::FixesRolledBack);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "one \ntwo\n");
+    }
+
+    #[test]
+    fn unstaged_changes_come_back_when_hooks_error_or_panic() {
+        let dir = partially_staged_repo();
+        let file = dir.path().join("a.txt");
+
+        let run = || -> Result<Unstash> {
+            let stash = stash_unstaged(dir.path())?;
+            std::fs::write(&file, "one\n")?;
+            Err(anyhow!("hook failed"))?;
+            stash.restore()
+        };
+        assert!(run().is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "one \ntwo\n");
+
+        let panicked = std::panic::catch_unwind(|| {
+            let _stash = stash_unstaged(dir.path()).unwrap();
+            panic!("hook panicked");
+        });
+        assert!(panicked.is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "one \ntwo\n");
+        let leftovers = std::fs::read_dir(dir.path().join(".git"))
+            .unwrap()
+            .filter(|e| {
+                let name = e.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with("precommit-rs-patch-")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn nothing_to_stash_without_unstaged_changes() {
+        let dir = tempdir().unwrap();
+        init_repo(dir.path());
+        std::fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        git(dir.path(), &["add", "."]);
+
+        let stash = stash_unstaged(dir.path()).unwrap();
+        assert!(stash.patch().is_none());
+        assert_eq!(stash.restore().unwrap(), Unstash::Applied);
+    }
+
+    fn commit_file(dir: &Path, name: &str, content: &str) -> String {
+        std::fs::write(dir.join(name), content).unwrap();
+        git(dir, &["add", name]);
+        git(dir, &["commit", "-q", "-m", name]);
+        git(dir, &["rev-parse", "HEAD"]).trim().to_string()
+    }
+
+    #[test]
+    fn pushed_files_cover_commits_missing_on_remote() {
+        let dir = tempdir().unwrap();
+        init_repo(dir.path());
+        let first = commit_file(dir.path(), "a.txt", "a\n");
+        commit_file(dir.path(), "b.txt", "b\n");
+        let head = commit_file(dir.path(), "c.txt", "c\n");
+        let root = repo_root(dir.path()).unwrap();
+
+        let line = format!("refs/heads/main {} refs/heads/main {}\n", head, first);
+        let files = pushed_files(dir.path(), "origin", &line).unwrap().unwrap();
+        assert_eq!(files, vec![root.join("b.txt"), root.join("c.txt")]);
+
+        // No remote history at all: everything should be checked
+        let zero = "0".repeat(40);
+        let line = format!("refs/heads/main {} refs/heads/main {}\n", head, zero);
+        assert!(pushed_files(dir.path(), "origin", &line).unwrap().is_none());
+
+        // Deleting a remote branch pushes nothing
+        let line = format!("(delete) {} refs/heads/old {}\n", zero, first);
+        let files = pushed_files(dir.path(), "origin", &line).unwrap().unwrap();
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn changed_files_skip_deletions_and_keep_rename_destinations() {
+        let dir = tempdir().unwrap();
+        init_repo(dir.path());
+        commit_file(dir.path(), "keep.txt", "keep\n");
+        commit_file(dir.path(), "gone.txt", "gone\n");
+        let base = commit_file(dir.path(), "old.txt", "same content\n");
+        git(dir.path(), &["mv", "old.txt", "new.txt"]);
+        git(dir.path(), &["rm", "-q", "gone.txt"]);
+        let head = commit_file(dir.path(), "keep.txt", "changed\n");
+        let root = repo_root(dir.path()).unwrap();
+
+        let files = changed_files(dir.path(), &base, &head).unwrap();
+        assert_eq!(files, vec![root.join("keep.txt"), root.join("new.txt")]);
+    }
+}
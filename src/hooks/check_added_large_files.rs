@@ -1,10 +1,11 @@
-use anyhow::Result;
+use super::{Hook, HookKind, HookOutcome};
+use anyhow::{anyhow, bail, Result};
 use ignore::WalkBuilder;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 /// Fail if any file path in `paths` exceeds `max_bytes` when specified.
-pub fn run(max_bytes: Option<u64>, paths: Vec<PathBuf>) -> Result<()> {
+pub fn run(max_bytes: Option<u64>, paths: Vec<PathBuf>) -> Result<HookOutcome> {
     run_with_ctx(&crate::RunContext::default(), max_bytes, paths)
 }
 
@@ -12,18 +13,18 @@ pub fn run_with_ctx(
     ctx: &crate::RunContext,
     max_bytes: Option<u64>,
     paths: Vec<PathBuf>,
-) -> Result<()> {
+) -> Result<HookOutcome> {
     if ctx.debug {
         eprintln!("check_added_large_files: dry_run={}", ctx.dry_run);
     }
-    let mut too_large = false;
+    let mut checked = false;
+    let mut problems = Vec::new();
     let limit = max_bytes.unwrap_or(500_000); // default 500 KB
 
     for p in paths {
         if p.is_file() {
-            if check_file(&p, limit)? {
-                too_large = true;
-            }
+            checked = true;
+            problems.extend(check_file(&p, limit)?);
             continue;
         }
 
@@ -60,41 +61,78 @@ pub fn run_with_ctx(
                     continue;
                 }
 
-                if check_file(entry.path(), limit)? {
-                    too_large = true;
-                }
+                checked = true;
+                problems.extend(check_file(entry.path(), limit)?);
             }
         } else if metadata.is_file() {
-            if check_file(&p, limit)? {
-                too_large = true;
-            }
+            checked = true;
+            problems.extend(check_file(&p, limit)?);
         }
     }
 
-    if too_large {
-        if ctx.dry_run {
-            if ctx.debug {
-                eprintln!("dry-run: check would have failed");
-            }
-            return Ok(());
-        }
-        std::process::exit(1);
-    }
-    Ok(())
+    Ok(HookOutcome::from_problems(ctx, checked, problems))
 }
 
-fn check_file(path: &Path, limit: u64) -> Result<bool> {
+fn check_file(path: &Path, limit: u64) -> Result<Option<String>> {
     let metadata = fs::metadata(path)?;
     if metadata.len() > limit {
-        eprintln!(
+        return Ok(Some(format!(
             "File {} is too large ({} bytes) > {} bytes",
             path.display(),
             metadata.len(),
             limit
-        );
-        return Ok(true);
+        )));
+    }
+    Ok(None)
+}
+
+/// Parse the configured args: either nothing, a bare byte count
+/// (`['500000']`) or `--max-bytes <n>` / `--max-bytes=<n>`.
+pub fn parse_max_bytes(args: &[String]) -> Result<Option<u64>> {
+    let value = match args {
+        [] => return Ok(None),
+        [flag, value] if flag == "--max-bytes" => value.as_str(),
+        [arg] => arg.strip_prefix("--max-bytes=").unwrap_or(arg),
+        _ => bail!("Expected a single max size in bytes, got {:?}", args),
+    };
+    value
+        .parse::<u64>()
+        .map(Some)
+        .map_err(|_| anyhow!("Invalid max size in bytes: '{}'", value))
+}
+
+/// `check-added-large-files`: fail on files larger than a size limit.
+pub struct CheckAddedLargeFiles;
+
+impl Hook for CheckAddedLargeFiles {
+    fn id(&self) -> &str {
+        "check-added-large-files"
+    }
+
+    fn description(&self) -> &str {
+        "Fail if added files exceed a size limit (in bytes)"
+    }
+
+    fn kind(&self) -> HookKind {
+        HookKind::Checker
+    }
+
+    fn check_args(&self, args: &[String]) -> Result<()> {
+        parse_max_bytes(args).map(|_| ())
+    }
+
+    fn run(
+        &self,
+        ctx: &crate::RunContext,
+        args: &[String],
+        paths: Vec<PathBuf>,
+    ) -> Result<HookOutcome> {
+        run_with_ctx(ctx, parse_max_bytes(args)?, paths)
+    }
+
+    fn failing_sample(&self) -> (&str, Vec<u8>) {
+        ("large.txt", vec![b'x'; 1_000_000])
     }
-    Ok(false)
 }
 
 #[cfg(test)]
@@ -108,22 +146,38 @@ mod tests {
         let data = vec![0u8; 1024 * 1024];
         std::io::Write::write_all(&mut f, &data).unwrap();
         let path = f.path().to_path_buf();
-        // Use a large limit so the function returns Ok instead of exiting
-        let res = run(Some(10_000_000), vec![path]);
-        assert!(res.is_ok());
+        let res = run(Some(10_000_000), vec![path.clone()]).unwrap();
+        assert_eq!(res, HookOutcome::Passed);
+
+        let res = run(Some(1024), vec![path]).unwrap();
+        assert_eq!(res.diagnostics().len(), 1);
     }
 
     #[test]
     fn skips_gitignored_directories() {
         let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
         std::fs::write(dir.path().join(".gitignore"), "ignored/\n").unwrap();
         let ignored_dir = dir.path().join("ignored");
         std::fs::create_dir_all(&ignored_dir).unwrap();
         std::fs::write(ignored_dir.join("large.bin"), vec![0u8; 2_000_000]).unwrap();
-        let mut ctx = crate::RunContext::default();
-        ctx.dry_run = true;
+        let res = run(Some(500_000), vec![dir.path().to_path_buf()]).unwrap();
+        assert!(res.is_success(), "gitignored files should be skipped");
+    }
 
-        let res = run_with_ctx(&ctx, Some(500_000), vec![dir.path().to_path_buf()]);
-        assert!(res.is_ok(), "gitignored files should be skipped");
+    #[test]
+    fn parses_size_args() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(parse_max_bytes(&[]).unwrap(), None);
+        assert_eq!(parse_max_bytes(&args(&["500"])).unwrap(), Some(500));
+        assert_eq!(
+            parse_max_bytes(&args(&["--max-bytes", "10"])).unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            parse_max_bytes(&args(&["--max-bytes=20"])).unwrap(),
+            Some(20)
+        );
+        assert!(parse_max_bytes(&args(&["big"])).is_err());
     }
 }
@@ -1,60 +1,89 @@
+use super::{Hook, HookKind, HookOutcome};
 use anyhow::Result;
 use std::fs;
 use std::path::PathBuf;
 
-pub fn run(paths: Vec<PathBuf>) -> Result<()> {
+pub fn run(paths: Vec<PathBuf>) -> Result<HookOutcome> {
     run_with_ctx(&crate::RunContext::default(), paths)
 }
 
-pub fn run_with_ctx(ctx: &crate::RunContext, paths: Vec<PathBuf>) -> Result<()> {
+pub fn run_with_ctx(ctx: &crate::RunContext, paths: Vec<PathBuf>) -> Result<HookOutcome> {
     if ctx.debug {
         eprintln!("check_yaml: dry_run={}", ctx.dry_run);
     }
-    let mut had_error = false;
-    for p in paths {
-        if p.is_file() {
-            ctx.changelog
-                .lock()
-                .unwrap()
-                .record_file_checked("check-yaml", &p);
+    let files: Vec<PathBuf> = paths.into_iter().filter(|p| p.is_file()).collect();
+    let results = crate::parallel::map_ordered(ctx.jobs, &files, |p| check_file(ctx, p));
 
-            let content = match fs::read_to_string(&p) {
-                Ok(s) => s,
-                Err(e) => {
-                    if e.kind() == std::io::ErrorKind::InvalidData {
-                        if ctx.debug {
-                            eprintln!("skipping non-utf8 file {}", p.display());
-                        }
-                        ctx.changelog.lock().unwrap().record_change(
-                            "check-yaml",
-                            &format!("Skipped non-UTF8 file: {}", p.display()),
-                        );
-                        continue;
-                    } else {
-                        return Err(e.into());
-                    }
+    let mut problems = Vec::new();
+    for result in results {
+        problems.extend(result?);
+    }
+    let checked = !files.is_empty();
+    Ok(HookOutcome::from_problems(ctx, checked, problems))
+}
+
+/// Problem found in `p`, if it is not valid YAML.
+fn check_file(ctx: &crate::RunContext, p: &PathBuf) -> Result<Option<String>> {
+    ctx.changelog
+        .lock()
+        .unwrap()
+        .record_file_checked("check-yaml", p);
+
+    let content = match fs::read_to_string(p) {
+        Ok(s) => s,
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::InvalidData {
+                if ctx.debug {
+                    eprintln!("skipping non-utf8 file {}", p.display());
                 }
-            };
-            if let Err(e) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
-                eprintln!("YAML parse error in {}: {}", p.display(), e);
                 ctx.changelog.lock().unwrap().record_change(
                     "check-yaml",
-                    &format!("Invalid YAML in {}: {}", p.display(), e),
+                    &format!("Skipped non-UTF8 file: {}", p.display()),
                 );
-                had_error = true;
+                return Ok(None);
+            } else {
+                return Err(e.into());
             }
         }
+    };
+    if let Err(e) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
+        ctx.changelog.lock().unwrap().record_change(
+            "check-yaml",
+            &format!("Invalid YAML in {}: {}", p.display(), e),
+        );
+        return Ok(Some(format!("YAML parse error in {}: {}", p.display(), e)));
     }
-    if had_error {
-        if ctx.dry_run {
-            if ctx.debug {
-                eprintln!("dry-run: check-yaml would have failed");
-            }
-            return Ok(());
-        }
-        std::process::exit(1);
+    Ok(None)
+}
+
+/// `check-yaml`: fail on files that do not parse as YAML.
+pub struct CheckYaml;
+
+impl Hook for CheckYaml {
+    fn id(&self) -> &str {
+        "check-yaml"
+    }
+
+    fn description(&self) -> &str {
+        "Validate YAML files"
+    }
+
+    fn kind(&self) -> HookKind {
+        HookKind::Checker
+    }
+
+    fn run(
+        &self,
+        ctx: &crate::RunContext,
+        _args: &[String],
+        paths: Vec<PathBuf>,
+    ) -> Result<HookOutcome> {
+        run_with_ctx(ctx, paths)
+    }
+
+    fn failing_sample(&self) -> (&str, Vec<u8>) {
+        ("invalid.yaml", b"invalid: [yaml: }".to_vec())
     }
-    Ok(())
 }
 
 #[cfg(test)]
@@ -66,7 +95,24 @@ mod tests {
     fn valid_yaml_ok() {
         let mut f = NamedTempFile::new().unwrap();
         std::io::Write::write_all(&mut f, b"a: 1\nb: [1,2]").unwrap();
-        let res = run(vec![f.path().to_path_buf()]);
-        assert!(res.is_ok());
+        let res = run(vec![f.path().to_path_buf()]).unwrap();
+        assert_eq!(res, HookOutcome::Passed);
+    }
+
+    #[test]
+    fn invalid_yaml_fails() {
+        let mut f = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut f, b"invalid: [yaml: }").unwrap();
+        let res = run(vec![f.path().to_path_buf()]).unwrap();
+        assert_eq!(res.diagnostics().len(), 1);
+        assert_eq!(res.exit_code(), 1);
+
+        // A dry run passes, like the fixers
+        let ctx = crate::RunContext {
+            dry_run: true,
+            ..Default::default()
+        };
+        let res = run_with_ctx(&ctx, vec![f.path().to_path_buf()]).unwrap();
+        assert_eq!(res, HookOutcome::Passed);
     }
 }
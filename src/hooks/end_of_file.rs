@@ -1,48 +1,32 @@
+use super::{Hook, HookKind, HookOutcome};
 use anyhow::Result;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
 
-pub fn run(paths: Vec<PathBuf>) -> Result<()> {
+pub fn run(paths: Vec<PathBuf>) -> Result<HookOutcome> {
     run_with_ctx(&crate::RunContext::default(), paths)
 }
 
-pub fn run_with_ctx(ctx: &crate::RunContext, paths: Vec<PathBuf>) -> Result<()> {
+pub fn run_with_ctx(ctx: &crate::RunContext, paths: Vec<PathBuf>) -> Result<HookOutcome> {
     if ctx.debug {
         eprintln!("end_of_file: dry_run={}", ctx.dry_run);
     }
-    let mut any_changes = false;
-    for path in paths {
-        if path.is_dir() {
-            for entry in walkdir::WalkDir::new(path)
-                .into_iter()
-                .filter_map(|e| e.ok())
-            {
-                let p = entry.path().to_path_buf();
-                if p.is_file() {
-                    if fix_file_with_ctx(ctx, &p)? {
-                        any_changes = true;
-                    }
-                }
-            }
-        } else if path.is_file() {
-            if fix_file_with_ctx(ctx, &path)? {
-                any_changes = true;
-            }
+    let files = super::files_in(paths);
+    let results = crate::parallel::map_ordered(ctx.jobs, &files, |p| fix_file_with_ctx(ctx, p));
+
+    let mut modified = Vec::new();
+    for (path, result) in files.iter().zip(results) {
+        if result? {
+            modified.push(path.clone());
         }
     }
 
-    if any_changes {
-        if ctx.dry_run {
-            if ctx.debug {
-                eprintln!("dry-run: end_of_file would change files");
-            }
-            return Ok(());
-        }
-        std::process::exit(1);
+    if ctx.dry_run && ctx.debug && !modified.is_empty() {
+        eprintln!("dry-run: end_of_file would change files");
     }
 
-    Ok(())
+    Ok(HookOutcome::from_fixes(ctx, !files.is_empty(), modified))
 }
 
 fn fix_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
@@ -52,6 +36,17 @@ fn fix_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
         .unwrap()
         .record_file_checked("end-of-file-fixer", path);
 
+    if !crate::identify::is_text(path)? {
+        if ctx.debug {
+            eprintln!("skipping binary file {}", path.display());
+        }
+        ctx.changelog.lock().unwrap().record_change(
+            "end-of-file-fixer",
+            &format!("Skipped binary file: {}", path.display()),
+        );
+        return Ok(false);
+    }
+
     let content = match fs::read_to_string(path) {
         Ok(s) => s,
         Err(e) => {
@@ -70,7 +65,7 @@ fn fix_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
         }
     };
     // Remove any trailing newlines then add exactly one
-    let trimmed = content.trim_end_matches(|c| c == '\n' || c == '\r');
+    let trimmed = content.trim_end_matches(['\n', '\r']);
     let new = format!("{}\n", trimmed);
     if new != content {
         if ctx.dry_run {
@@ -102,6 +97,32 @@ fn fix_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
     }
 }
 
+/// `end-of-file-fixer`: make every file end with exactly one newline.
+pub struct EndOfFileFixer;
+
+impl Hook for EndOfFileFixer {
+    fn id(&self) -> &str {
+        "end-of-file-fixer"
+    }
+
+    fn description(&self) -> &str {
+        "Ensure file ends with a single newline"
+    }
+
+    fn kind(&self) -> HookKind {
+        HookKind::Fixer
+    }
+
+    fn run(
+        &self,
+        ctx: &crate::RunContext,
+        _args: &[String],
+        paths: Vec<PathBuf>,
+    ) -> Result<HookOutcome> {
+        run_with_ctx(ctx, paths)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,10 +133,21 @@ mod tests {
         let dir = tempdir().unwrap();
         let file = dir.path().join("b.txt");
         std::fs::write(&file, "x\n\n\n").unwrap();
-        let mut ctx = crate::RunContext::default();
+        let ctx = crate::RunContext::default();
         let changed = fix_file_with_ctx(&ctx, &file).unwrap();
         assert!(changed);
         let new = std::fs::read_to_string(&file).unwrap();
         assert_eq!(new, "x\n");
     }
+
+    #[test]
+    fn leaves_binary_files_alone() {
+        let dir = tempdir().unwrap();
+        // Valid UTF-8, so only the binary sniff can tell
+        let file = dir.path().join("blob.bin");
+        std::fs::write(&file, b"x\0y").unwrap();
+        let ctx = crate::RunContext::default();
+        assert!(!fix_file_with_ctx(&ctx, &file).unwrap());
+        assert_eq!(std::fs::read(&file).unwrap(), b"x\0y");
+    }
 }
@@ -2,4 +2,149 @@ pub mod check_added_large_files;
 pub mod check_yaml;
 pub mod end_of_file;
 pub mod pretty_format_json;
+pub mod registry;
 pub mod trailing_whitespace;
+
+pub use registry::HookRegistry;
+
+use anyhow::{bail, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Files in `paths`, walking into directories, in a stable order so results
+/// can be reported deterministically when files are processed in parallel.
+/// Symlinks are left out: fixing one would rewrite the file it points to.
+pub(crate) fn files_in(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            for entry in walkdir::WalkDir::new(path)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+            {
+                if entry.file_type().is_file() {
+                    files.push(entry.into_path());
+                }
+            }
+        } else if metadata.is_file() {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Whether a hook rewrites files or only reports problems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    Fixer,
+    Checker,
+}
+
+/// A hook that can be run from a config file by id.
+///
+/// The built-in hooks implement this trait and are collected in
+/// [`HookRegistry::builtin`]; other crates can add their own implementations
+/// to a registry and hand it to `run_config` through [`crate::RunContext`].
+pub trait Hook: Send + Sync {
+    /// Id used in the `id:` field of the config.
+    fn id(&self) -> &str;
+
+    /// One-line description shown in help and listings.
+    fn description(&self) -> &str;
+
+    fn kind(&self) -> HookKind;
+
+    /// Check the `args` configured for the hook before it runs, rejecting
+    /// anything it does not understand. This only validates them: `run` is
+    /// given the same `args` and interprets them itself. Hooks take no
+    /// arguments unless they override this.
+    fn check_args(&self, args: &[String]) -> Result<()> {
+        if !args.is_empty() {
+            bail!("Hook '{}' does not accept args: {:?}", self.id(), args);
+        }
+        Ok(())
+    }
+
+    /// Run the hook over `paths`, which may contain files or directories.
+    fn run(
+        &self,
+        ctx: &crate::RunContext,
+        args: &[String],
+        paths: Vec<PathBuf>,
+    ) -> Result<HookOutcome>;
+
+    /// File name and contents that should make the hook report `Modified`
+    /// or `Failed`; used by `validate-hook`.
+    fn failing_sample(&self) -> (&str, Vec<u8>) {
+        // No newline at the end and nothing else to fix
+        ("needs-fixing.txt", b"test content".to_vec())
+    }
+}
+
+/// Result of running a hook over a set of paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    /// Every file the hook looked at was already acceptable.
+    Passed,
+    /// The hook rewrote these files (in dry-run mode: would have rewritten).
+    Modified(Vec<PathBuf>),
+    /// The hook found problems it cannot fix, one message per problem.
+    Failed(Vec<String>),
+    /// None of the paths were files the hook could look at.
+    Skipped,
+    /// The hook's command ran longer than this and was killed.
+    TimedOut(Duration),
+}
+
+impl HookOutcome {
+    /// Outcome of a fixer that looked at files (`checked`) and changed
+    /// `modified`. A dry run wrote nothing and only records what would change
+    /// in the changelog, so it passes like it always has.
+    pub fn from_fixes(ctx: &crate::RunContext, checked: bool, modified: Vec<PathBuf>) -> Self {
+        if !modified.is_empty() && !ctx.dry_run {
+            HookOutcome::Modified(modified)
+        } else if checked {
+            HookOutcome::Passed
+        } else {
+            HookOutcome::Skipped
+        }
+    }
+
+    /// Outcome of a checker that looked at files (`checked`) and found
+    /// `problems`. A dry run passes like a fixer's does.
+    pub fn from_problems(ctx: &crate::RunContext, checked: bool, problems: Vec<String>) -> Self {
+        if !problems.is_empty() && !ctx.dry_run {
+            HookOutcome::Failed(problems)
+        } else if checked {
+            HookOutcome::Passed
+        } else {
+            HookOutcome::Skipped
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, HookOutcome::Passed | HookOutcome::Skipped)
+    }
+
+    /// Messages explaining a failure; empty for every other outcome.
+    pub fn diagnostics(&self) -> &[String] {
+        match self {
+            HookOutcome::Failed(messages) => messages,
+            _ => &[],
+        }
+    }
+
+    /// Process exit code following pre-commit conventions: `1` when files were
+    /// modified or problems were found.
+    pub fn exit_code(&self) -> i32 {
+        if self.is_success() {
+            0
+        } else {
+            1
+        }
+    }
+}
@@ -1,36 +1,45 @@
+use super::{Hook, HookKind, HookOutcome};
 use anyhow::Result;
 use std::fs;
 use std::path::PathBuf;
 
-pub fn run(paths: Vec<PathBuf>) -> Result<()> {
+pub fn run(paths: Vec<PathBuf>) -> Result<HookOutcome> {
     run_with_ctx(&crate::RunContext::default(), paths)
 }
 
-pub fn run_with_ctx(ctx: &crate::RunContext, paths: Vec<PathBuf>) -> Result<()> {
+pub fn run_with_ctx(ctx: &crate::RunContext, paths: Vec<PathBuf>) -> Result<HookOutcome> {
     if ctx.debug {
         eprintln!("pretty_format_json: dry_run={}", ctx.dry_run);
     }
-    let mut any_changes = false;
-    for p in paths {
-        if p.is_file() {
-            if format_file_with_ctx(ctx, &p)? {
-                any_changes = true;
-            }
+    let files: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|p| fs::symlink_metadata(p).is_ok_and(|m| m.is_file()))
+        .collect();
+    let results = crate::parallel::map_ordered(ctx.jobs, &files, |p| format_file_with_ctx(ctx, p));
+
+    let mut modified = Vec::new();
+    for (path, result) in files.iter().zip(results) {
+        if result? {
+            modified.push(path.clone());
         }
     }
-    if any_changes {
-        if ctx.dry_run {
-            if ctx.debug {
-                eprintln!("dry-run: pretty_format_json would have changed files");
-            }
-            return Ok(());
-        }
-        std::process::exit(1);
+    if ctx.dry_run && ctx.debug && !modified.is_empty() {
+        eprintln!("dry-run: pretty_format_json would have changed files");
     }
-    Ok(())
+    Ok(HookOutcome::from_fixes(ctx, !files.is_empty(), modified))
 }
 
 fn format_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
+    if !crate::identify::is_text(path)? {
+        if ctx.debug {
+            eprintln!("skipping binary file {}", path.display());
+        }
+        ctx.changelog.lock().unwrap().record_change(
+            "pretty-format-json",
+            &format!("Skipped binary file: {}", path.display()),
+        );
+        return Ok(false);
+    }
     let content = match fs::read_to_string(path) {
         Ok(s) => s,
         Err(e) => {
@@ -75,6 +84,36 @@ fn format_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool>
     }
 }
 
+/// `pretty-format-json`: rewrite JSON files with consistent indentation.
+pub struct PrettyFormatJson;
+
+impl Hook for PrettyFormatJson {
+    fn id(&self) -> &str {
+        "pretty-format-json"
+    }
+
+    fn description(&self) -> &str {
+        "Pretty-format JSON files (in-place)"
+    }
+
+    fn kind(&self) -> HookKind {
+        HookKind::Fixer
+    }
+
+    fn run(
+        &self,
+        ctx: &crate::RunContext,
+        _args: &[String],
+        paths: Vec<PathBuf>,
+    ) -> Result<HookOutcome> {
+        run_with_ctx(ctx, paths)
+    }
+
+    fn failing_sample(&self) -> (&str, Vec<u8>) {
+        ("needs-fixing.json", b"{\"a\":1}".to_vec())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
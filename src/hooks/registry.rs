@@ -0,0 +1,118 @@
+use super::Hook;
+use std::fmt;
+use std::sync::Arc;
+
+/// Hooks that can be referenced by id from a config file.
+#[derive(Clone, Default)]
+pub struct HookRegistry {
+    hooks: Vec<Arc<dyn Hook>>,
+}
+
+impl HookRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry containing every hook bundled with precommit-rs.
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(super::trailing_whitespace::TrailingWhitespace);
+        registry.register(super::end_of_file::EndOfFileFixer);
+        registry.register(super::check_yaml::CheckYaml);
+        registry.register(super::pretty_format_json::PrettyFormatJson);
+        registry.register(super::check_added_large_files::CheckAddedLargeFiles);
+        registry
+    }
+
+    /// Add `hook`, replacing any hook already registered under the same id.
+    pub fn register<H: Hook + 'static>(&mut self, hook: H) {
+        let hook: Arc<dyn Hook> = Arc::new(hook);
+        match self.hooks.iter_mut().find(|h| h.id() == hook.id()) {
+            Some(existing) => *existing = hook,
+            None => self.hooks.push(hook),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn Hook> {
+        self.hooks.iter().find(|h| h.id() == id).map(|h| h.as_ref())
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Registered hooks in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Hook> {
+        self.hooks.iter().map(|h| h.as_ref())
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        self.iter().map(|h| h.id()).collect()
+    }
+}
+
+impl fmt::Debug for HookRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.ids()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::{HookKind, HookOutcome};
+    use std::path::PathBuf;
+
+    struct AlwaysPasses;
+
+    impl Hook for AlwaysPasses {
+        fn id(&self) -> &str {
+            "always-passes"
+        }
+
+        fn description(&self) -> &str {
+            "Does nothing"
+        }
+
+        fn kind(&self) -> HookKind {
+            HookKind::Checker
+        }
+
+        fn run(
+            &self,
+            _ctx: &crate::RunContext,
+            _args: &[String],
+            _paths: Vec<PathBuf>,
+        ) -> anyhow::Result<HookOutcome> {
+            Ok(HookOutcome::Passed)
+        }
+    }
+
+    #[test]
+    fn builtin_registry_lists_bundled_hooks() {
+        let registry = HookRegistry::builtin();
+        assert_eq!(
+            registry.ids(),
+            vec![
+                "trailing-whitespace",
+                "end-of-file-fixer",
+                "check-yaml",
+                "pretty-format-json",
+                "check-added-large-files",
+            ]
+        );
+    }
+
+    #[test]
+    fn third_party_hooks_can_be_registered() {
+        let mut registry = HookRegistry::builtin();
+        registry.register(AlwaysPasses);
+        let hook = registry.get("always-passes").unwrap();
+        assert_eq!(hook.kind(), HookKind::Checker);
+        assert!(hook.check_args(&["x".to_string()]).is_err());
+
+        registry.register(AlwaysPasses);
+        assert_eq!(registry.ids().len(), 6);
+    }
+}
@@ -1,77 +1,58 @@
+use super::{Hook, HookKind, HookOutcome};
 use anyhow::Result;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
 
-pub fn run(paths: Vec<PathBuf>) -> Result<()> {
+pub fn run(paths: Vec<PathBuf>) -> Result<HookOutcome> {
     run_with_ctx(&crate::RunContext::default(), paths)
 }
 
-pub fn run_with_ctx(ctx: &crate::RunContext, paths: Vec<PathBuf>) -> Result<()> {
+pub fn run_with_ctx(ctx: &crate::RunContext, paths: Vec<PathBuf>) -> Result<HookOutcome> {
     if ctx.debug {
         eprintln!("trailing_whitespace: dry_run={}", ctx.dry_run);
     }
-    let mut any_changes = false;
-    for path in paths {
-        if path.is_dir() {
-            for entry in walkdir::WalkDir::new(path)
-                .into_iter()
-                .filter_map(|e| e.ok())
-            {
-                let p = entry.path().to_path_buf();
-                if p.is_file() {
-                    if ctx.debug {
-                        eprintln!("processing {}", p.display());
-                    }
-                    match fix_file_with_ctx(ctx, &p) {
-                        Ok(changed) => {
-                            if changed {
-                                any_changes = true
-                            }
-                        }
-                        Err(e) => {
-                            if ctx.debug {
-                                eprintln!("error processing {}: {}", p.display(), e);
-                                continue;
-                            }
-                            return Err(e);
-                        }
-                    }
-                }
-            }
-        } else if path.is_file() {
-            match fix_file_with_ctx(ctx, &path) {
-                Ok(changed) => {
-                    if changed {
-                        any_changes = true
-                    }
-                }
-                Err(e) => {
-                    if ctx.debug {
-                        eprintln!("error processing {}: {}", path.display(), e);
-                        continue;
-                    }
-                    return Err(e);
+    let files = super::files_in(paths);
+    let results = crate::parallel::map_ordered(ctx.jobs, &files, |p| {
+        if ctx.debug {
+            eprintln!("processing {}", p.display());
+        }
+        fix_file_with_ctx(ctx, p)
+    });
+
+    let mut modified = Vec::new();
+    for (path, result) in files.iter().zip(results) {
+        match result {
+            Ok(true) => modified.push(path.clone()),
+            Ok(false) => {}
+            Err(e) => {
+                if ctx.debug {
+                    eprintln!("error processing {}: {}", path.display(), e);
+                    continue;
                 }
+                return Err(e);
             }
         }
     }
 
-    if any_changes {
-        if ctx.dry_run {
-            if ctx.debug {
-                eprintln!("dry-run: changes would have been made");
-            }
-            return Ok(());
-        }
-        // pre-commit expects exit code 1 when changes are made
-        std::process::exit(1);
+    if ctx.dry_run && ctx.debug && !modified.is_empty() {
+        eprintln!("dry-run: changes would have been made");
     }
 
-    Ok(())
+    Ok(HookOutcome::from_fixes(ctx, !files.is_empty(), modified))
 }
 
 fn fix_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
+    if !crate::identify::is_text(path)? {
+        if ctx.debug {
+            eprintln!("skipping binary file {}", path.display());
+        }
+        ctx.changelog.lock().unwrap().record_change(
+            "trailing-whitespace",
+            &format!("Skipped binary file: {}", path.display()),
+        );
+        return Ok(false);
+    }
     let content = match fs::read_to_string(path) {
         Ok(s) => s,
         Err(e) => {
@@ -79,6 +60,10 @@ fn fix_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
                 if ctx.debug {
                     eprintln!("skipping non-utf8 file {}", path.display());
                 }
+                ctx.changelog.lock().unwrap().record_change(
+                    "trailing-whitespace",
+                    &format!("Skipped non-UTF8 file: {}", path.display()),
+                );
                 return Ok(false);
             } else {
                 return Err(e.into());
@@ -89,7 +74,7 @@ fn fix_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
     let mut out = String::with_capacity(content.len());
 
     for line in content.lines() {
-        let trimmed = line.trim_end_matches(|c| c == ' ' || c == '\t');
+        let trimmed = line.trim_end_matches([' ', '\t']);
         if trimmed.len() != line.len() {
             changed = true;
         }
@@ -129,6 +114,36 @@ fn fix_file_with_ctx(ctx: &crate::RunContext, path: &PathBuf) -> Result<bool> {
     Ok(changed)
 }
 
+/// `trailing-whitespace`: strip spaces and tabs at the end of lines.
+pub struct TrailingWhitespace;
+
+impl Hook for TrailingWhitespace {
+    fn id(&self) -> &str {
+        "trailing-whitespace"
+    }
+
+    fn description(&self) -> &str {
+        "Fix trailing whitespace in files"
+    }
+
+    fn kind(&self) -> HookKind {
+        HookKind::Fixer
+    }
+
+    fn run(
+        &self,
+        ctx: &crate::RunContext,
+        _args: &[String],
+        paths: Vec<PathBuf>,
+    ) -> Result<HookOutcome> {
+        run_with_ctx(ctx, paths)
+    }
+
+    fn failing_sample(&self) -> (&str, Vec<u8>) {
+        ("needs-fixing.txt", b"test content \t\n".to_vec())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,333 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Set of type tags describing a file, e.g. `{"file", "text", "python"}`.
+pub type Tags = BTreeSet<&'static str>;
+
+/// Tags describing what kind of filesystem entry a path is.
+const KIND_TAGS: &[&str] = &[
+    "file",
+    "directory",
+    "symlink",
+    "executable",
+    "non-executable",
+    "text",
+    "binary",
+];
+
+/// Tags implied by a file extension (lowercased, without the dot).
+const EXTENSIONS: &[(&str, &[&str])] = &[
+    ("bash", &["text", "shell", "bash"]),
+    ("bat", &["text", "batch"]),
+    ("c", &["text", "c"]),
+    ("cc", &["text", "c++"]),
+    ("cfg", &["text"]),
+    ("cpp", &["text", "c++"]),
+    ("css", &["text", "css"]),
+    ("csv", &["text", "csv"]),
+    ("gif", &["binary", "image", "gif"]),
+    ("go", &["text", "go"]),
+    ("gz", &["binary", "gzip"]),
+    ("h", &["text", "header", "c"]),
+    ("hpp", &["text", "header", "c++"]),
+    ("html", &["text", "html"]),
+    ("ico", &["binary", "icon"]),
+    ("ini", &["text", "ini"]),
+    ("java", &["text", "java"]),
+    ("jpeg", &["binary", "image", "jpeg"]),
+    ("jpg", &["binary", "image", "jpeg"]),
+    ("js", &["text", "javascript"]),
+    ("json", &["text", "json"]),
+    ("jsonc", &["text", "jsonc"]),
+    ("jsx", &["text", "jsx"]),
+    ("lock", &["text"]),
+    ("md", &["text", "markdown"]),
+    ("pdf", &["binary", "pdf"]),
+    ("pl", &["text", "perl"]),
+    ("png", &["binary", "image", "png"]),
+    ("py", &["text", "python"]),
+    ("pyi", &["text", "pyi"]),
+    ("rb", &["text", "ruby"]),
+    ("rs", &["text", "rust"]),
+    ("sh", &["text", "shell", "sh"]),
+    ("sql", &["text", "sql"]),
+    ("svg", &["text", "image", "svg", "xml"]),
+    ("tar", &["binary", "tar"]),
+    ("toml", &["text", "toml"]),
+    ("ts", &["text", "ts"]),
+    ("tsx", &["text", "tsx"]),
+    ("txt", &["text", "plain-text"]),
+    ("xml", &["text", "xml"]),
+    ("yaml", &["text", "yaml"]),
+    ("yml", &["text", "yaml"]),
+    ("zip", &["binary", "zip"]),
+    ("zsh", &["text", "shell", "zsh"]),
+];
+
+/// Tags implied by well-known file names without a telling extension.
+const FILENAMES: &[(&str, &[&str])] = &[
+    (".bashrc", &["text", "shell", "bash"]),
+    (".editorconfig", &["text", "editorconfig"]),
+    (".gitattributes", &["text", "gitattributes"]),
+    (".gitignore", &["text", "gitignore"]),
+    (".gitmodules", &["text", "gitmodules"]),
+    (".pre-commit.yaml", &["text", "yaml"]),
+    (".zshrc", &["text", "shell", "zsh"]),
+    ("Cargo.lock", &["text", "toml"]),
+    ("Dockerfile", &["text", "dockerfile"]),
+    ("LICENSE", &["text", "plain-text"]),
+    ("Makefile", &["text", "makefile"]),
+    ("go.mod", &["text", "go-mod"]),
+    ("go.sum", &["text", "go-sum"]),
+    ("makefile", &["text", "makefile"]),
+];
+
+/// Tags implied by the interpreter named in a `#!` line.
+const INTERPRETERS: &[(&str, &[&str])] = &[
+    ("ash", &["shell", "ash"]),
+    ("bash", &["shell", "bash"]),
+    ("dash", &["shell", "dash"]),
+    ("ksh", &["shell", "ksh"]),
+    ("node", &["javascript"]),
+    ("perl", &["perl"]),
+    ("python", &["python"]),
+    ("python2", &["python", "python2"]),
+    ("python3", &["python", "python3"]),
+    ("ruby", &["ruby"]),
+    ("sh", &["shell", "sh"]),
+    ("zsh", &["shell", "zsh"]),
+];
+
+/// Bytes read from the start of a file to decide whether it is text.
+const SNIFF_LEN: usize = 1024;
+
+/// Every tag `identify` can produce, sorted and without duplicates.
+pub fn known_tags() -> Vec<&'static str> {
+    let mut tags: Vec<&'static str> = [EXTENSIONS, FILENAMES, INTERPRETERS]
+        .iter()
+        .flat_map(|table| table.iter())
+        .flat_map(|(_, tags)| tags.iter().copied())
+        .chain(KIND_TAGS.iter().copied())
+        .collect();
+    tags.sort_unstable();
+    tags.dedup();
+    tags
+}
+
+/// Whether `tag` is one `identify` can produce, for validating the
+/// `types`, `types_or` and `exclude_types` of a hook.
+pub fn is_known_tag(tag: &str) -> bool {
+    KIND_TAGS.contains(&tag)
+        || [EXTENSIONS, FILENAMES, INTERPRETERS]
+            .iter()
+            .flat_map(|table| table.iter())
+            .any(|(_, tags)| tags.contains(&tag))
+}
+
+fn lookup(table: &[(&str, &'static [&'static str])], key: &str) -> Option<&'static [&'static str]> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, tags)| *tags)
+}
+
+/// Tags from the file name alone: well-known names first, then the extension.
+pub fn tags_from_filename(path: &Path) -> Tags {
+    let mut tags = Tags::new();
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return tags;
+    };
+    if let Some(found) = lookup(FILENAMES, name) {
+        tags.extend(found);
+    } else if let Some((_, ext)) = name.rsplit_once('.') {
+        if let Some(found) = lookup(EXTENSIONS, &ext.to_ascii_lowercase()) {
+            tags.extend(found);
+        }
+    }
+    tags
+}
+
+/// Tags from a `#!` line such as `#!/usr/bin/env python3` or `#!/bin/sh -e`.
+pub fn tags_from_shebang(head: &[u8]) -> Tags {
+    let mut tags = Tags::new();
+    let Some(line) = head.strip_prefix(b"#!") else {
+        return tags;
+    };
+    let line = line.split(|b| *b == b'\n').next().unwrap_or_default();
+    let line = String::from_utf8_lossy(line);
+    let mut words = line.split_whitespace();
+    let Some(mut interpreter) = words.next().map(|w| w.rsplit('/').next().unwrap_or(w)) else {
+        return tags;
+    };
+    if interpreter == "env" {
+        // Skip env options such as `-S`
+        match words.find(|w| !w.starts_with('-')) {
+            Some(program) => interpreter = program,
+            None => return tags,
+        }
+    }
+    if let Some(found) = lookup(INTERPRETERS, interpreter) {
+        tags.extend(found);
+    } else if let Some(found) = INTERPRETERS
+        .iter()
+        .find(|(name, _)| {
+            // Versioned names such as python3.12
+            interpreter
+                .strip_prefix(name)
+                .is_some_and(|v| v.starts_with(|c: char| c.is_ascii_digit() || c == '.'))
+        })
+        .map(|(_, tags)| *tags)
+    {
+        tags.extend(found);
+    }
+    tags
+}
+
+/// Whether the start of a file looks like text: no NUL bytes and only a
+/// small share of control characters other than whitespace.
+pub fn is_text_content(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    let control = head
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\n' | b'\r' | b'\t' | 0x0c | 0x1b | 0x08))
+        .count();
+    control * 10 <= head.len()
+}
+
+fn read_head(path: &Path) -> Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(head)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Classify `path`. Symlinks are not followed and only get `symlink`;
+/// directories only get `directory`. Files get `file`, `executable` or
+/// `non-executable`, `text` or `binary`, and any language tags from their
+/// name or, failing that, their shebang line.
+pub fn tags_from_path(path: &Path) -> Result<Tags> {
+    let metadata = fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+    let mut tags = Tags::new();
+    if metadata.file_type().is_symlink() {
+        tags.insert("symlink");
+        return Ok(tags);
+    }
+    if metadata.is_dir() {
+        tags.insert("directory");
+        return Ok(tags);
+    }
+
+    tags.insert("file");
+    let executable = is_executable(&metadata);
+    tags.insert(if executable {
+        "executable"
+    } else {
+        "non-executable"
+    });
+
+    let name_tags = tags_from_filename(path);
+    let knows_encoding = name_tags.contains("text") || name_tags.contains("binary");
+    let has_language = name_tags.iter().any(|tag| !KIND_TAGS.contains(tag));
+    tags.extend(name_tags);
+
+    // Only sniff the content when the name does not settle it
+    if !knows_encoding || (executable && !has_language) {
+        let head = read_head(path)?;
+        if !knows_encoding {
+            tags.insert(if is_text_content(&head) {
+                "text"
+            } else {
+                "binary"
+            });
+        }
+        if executable && !has_language {
+            tags.extend(tags_from_shebang(&head));
+        }
+    }
+    Ok(tags)
+}
+
+/// Whether `path` is a regular file with text content. Used by the built-in
+/// text fixers to leave binary files alone.
+pub fn is_text(path: &Path) -> Result<bool> {
+    Ok(tags_from_path(path)?.contains("text"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn classifies_by_name_extension_and_shebang() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, content: &[u8]| {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+
+        let tags = tags_from_path(&write("lib.rs", b"fn main() {}\n")).unwrap();
+        assert_eq!(tags, Tags::from(["file", "non-executable", "rust", "text"]));
+        assert!(tags_from_path(&write("Makefile", b"all:\n"))
+            .unwrap()
+            .contains("makefile"));
+        assert!(tags_from_path(&write("config.YML", b"a: 1\n"))
+            .unwrap()
+            .contains("yaml"));
+
+        let unknown = tags_from_path(&write("data", b"\x89PNG\0\0")).unwrap();
+        assert!(unknown.contains("binary"));
+        let notes = tags_from_path(&write("NOTES", b"just text\n")).unwrap();
+        assert!(notes.contains("text"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = write("deploy", b"#!/usr/bin/env bash\nset -e\n");
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+            let tags = tags_from_path(&script).unwrap();
+            for tag in ["executable", "text", "shell", "bash"] {
+                assert!(tags.contains(tag), "{:?}", tags);
+            }
+
+            let link = dir.path().join("link.rs");
+            std::os::unix::fs::symlink(&script, &link).unwrap();
+            assert_eq!(tags_from_path(&link).unwrap(), Tags::from(["symlink"]));
+        }
+    }
+
+    #[test]
+    fn parses_shebang_variants() {
+        assert!(tags_from_shebang(b"#!/bin/sh -e\n").contains("sh"));
+        assert!(tags_from_shebang(b"#!/usr/bin/env -S python3 -u\n").contains("python3"));
+        assert!(tags_from_shebang(b"#!/usr/bin/python3.12\n").contains("python"));
+        assert!(tags_from_shebang(b"#!/usr/bin/env\n").is_empty());
+        assert!(tags_from_shebang(b"echo hi\n").is_empty());
+    }
+
+    #[test]
+    fn known_tags() {
+        assert!(is_known_tag("text"));
+        assert!(is_known_tag("python"));
+        assert!(is_known_tag("shell"));
+        assert!(!is_known_tag("pyhton"));
+    }
+}
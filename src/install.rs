@@ -0,0 +1,456 @@
+use crate::config::HookType;
+use crate::git;
+use anyhow::{bail, Context, Result};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Determine the precommit-rs binary the git hook scripts should call:
+/// 1. `path` if provided
+/// 2. `which precommit-rs`
+/// 3. `<repo_root>/target/release/precommit-rs`
+pub fn resolve_binary(path: Option<String>, repo_root: &Path, debug: bool) -> String {
+    if let Some(p) = path {
+        return p;
+    }
+
+    // Try to find installed binary with `which`
+    let which_out = Command::new("which").arg("precommit-rs").output();
+    match which_out {
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).trim().to_string(),
+        _ => {
+            // Fall back to local release binary
+            let local_bin = repo_root
+                .join("target/release/precommit-rs")
+                .to_string_lossy()
+                .to_string();
+            if debug {
+                eprintln!("No installed binary found, using {}", local_bin);
+            }
+            local_bin
+        }
+    }
+}
+
+/// `path` with its longest existing ancestor canonicalized, so paths that do
+/// not exist yet can still be compared with ones that do.
+fn normalize(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+    let mut normalized = existing
+        .canonicalize()
+        .unwrap_or_else(|_| existing.to_path_buf());
+    normalized.extend(rest.iter().rev());
+    normalized
+}
+
+/// Directory `install` writes hook scripts to, and `uninstall` removes them
+/// from, for the repository containing `dir`, as resolved by
+/// `git rev-parse --git-path hooks`.
+///
+/// When `core.hooksPath` points outside both the work tree and the git
+/// directory the hooks are shared with other repositories, so this fails
+/// unless `force` is set.
+pub fn hooks_dir(dir: &Path, force: bool) -> Result<PathBuf> {
+    let hooks = git::hooks_dir(dir)?;
+    let Some(configured) = git::config_value(dir, "core.hooksPath")? else {
+        return Ok(hooks);
+    };
+
+    let normalized = normalize(&hooks);
+    let inside = [git::repo_root(dir)?, git::common_dir(dir)?]
+        .iter()
+        .any(|base| normalized.starts_with(normalize(base)));
+    if !inside {
+        if !force {
+            bail!(
+                "core.hooksPath is set to '{}' ({}), which is outside this repository; \
+                use --force to use it anyway",
+                configured,
+                hooks.display()
+            );
+        }
+        eprintln!(
+            "Using {} from core.hooksPath outside the repository",
+            hooks.display()
+        );
+    }
+    Ok(hooks)
+}
+
+/// Line identifying scripts written by `install`; `uninstall` only removes
+/// hooks containing it.
+pub const SIGNATURE: &str = "# precommit-rs generated hook: remove with `precommit-rs uninstall`";
+
+/// Comment written by versions of `install` that predate [`SIGNATURE`].
+const OLD_SIGNATURE: &str = "# Run pre-commit hooks using ";
+
+/// Suffix given to a hook that existed before `install` replaced it.
+const LEGACY_SUFFIX: &str = ".legacy";
+
+/// Whether the hook at `path` was written by precommit-rs.
+pub fn is_ours(path: &Path) -> bool {
+    match fs::read_to_string(path) {
+        Ok(script) => {
+            script.contains(SIGNATURE)
+                || (script.contains(OLD_SIGNATURE) && script.contains("run-config"))
+        }
+        Err(_) => false,
+    }
+}
+
+fn legacy_path(hook_path: &Path) -> PathBuf {
+    let mut name = hook_path.file_name().unwrap_or_default().to_os_string();
+    name.push(LEGACY_SUFFIX);
+    hook_path.with_file_name(name)
+}
+
+/// Script installed as `.git/hooks/<hook_type>`. Git's arguments are passed
+/// through after `--` and stdin is inherited through `exec`, so run-config
+/// sees the commit message file or the pre-push ref lines.
+///
+/// With `chain_legacy` the script first runs `<hook_type>.legacy` if it is
+/// executable and stops if it fails. pre-push hooks read stdin, so it is
+/// buffered and handed to both.
+pub fn hook_script(hook_type: HookType, binary: &str, chain_legacy: bool) -> String {
+    let stage = hook_type.as_str();
+    let mut script = format!("#!/usr/bin/env bash\n{}\nset -e\n\n", SIGNATURE);
+    let reads_stdin = hook_type == HookType::PrePush;
+    if chain_legacy {
+        if reads_stdin {
+            script.push_str("stdin=\"$(cat)\"\n");
+        }
+        let feed = if reads_stdin {
+            "printf '%s\\n' \"$stdin\" | "
+        } else {
+            ""
+        };
+        let _ = write!(
+            script,
+            "legacy=\"$(dirname \"$0\")/{stage}{suffix}\"\n\
+            if [ -x \"$legacy\" ]; then\n\
+            \x20   {feed}\"$legacy\" \"$@\"\n\
+            fi\n\n",
+            stage = stage,
+            suffix = LEGACY_SUFFIX,
+            feed = feed,
+        );
+        if reads_stdin {
+            let _ = write!(
+                script,
+                "# Run {stage} hooks using {binary}\n\
+                printf '%s\\n' \"$stdin\" | \
+                exec \"{binary}\" run-config --hook-stage {stage} -- \"$@\"\n",
+                stage = stage,
+                binary = binary,
+            );
+            return script;
+        }
+    }
+    let _ = write!(
+        script,
+        "# Run {stage} hooks using {binary}\n\
+        exec \"{binary}\" run-config --hook-stage {stage} -- \"$@\"\n",
+        stage = stage,
+        binary = binary,
+    );
+    script
+}
+
+/// Write the script for `hook_type` into `hooks_dir` and make it executable.
+///
+/// A hook that was not written by precommit-rs is first moved to
+/// `<hook_type>.legacy`; if that file already exists the install is refused
+/// rather than losing either hook.
+pub fn write_hook(
+    hooks_dir: &Path,
+    hook_type: HookType,
+    binary: &str,
+    chain_legacy: bool,
+) -> Result<PathBuf> {
+    fs::create_dir_all(hooks_dir)
+        .with_context(|| format!("Failed to create {}", hooks_dir.display()))?;
+    let hook_path = hooks_dir.join(hook_type.as_str());
+
+    if hook_path.exists() && !is_ours(&hook_path) {
+        let legacy = legacy_path(&hook_path);
+        if legacy.exists() {
+            bail!(
+                "{} was not written by precommit-rs and {} already exists; move one of them away and re-run install",
+                hook_path.display(),
+                legacy.display()
+            );
+        }
+        fs::rename(&hook_path, &legacy)
+            .with_context(|| format!("Failed to move {}", hook_path.display()))?;
+        println!(
+            "Moved existing hook {} to {}",
+            hook_path.display(),
+            legacy.display()
+        );
+    }
+
+    fs::write(&hook_path, hook_script(hook_type, binary, chain_legacy))
+        .with_context(|| format!("Failed to write {}", hook_path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(&hook_path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(&hook_path, perms)?;
+    }
+
+    Ok(hook_path)
+}
+
+/// What `remove_hook` did for one hook type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Removal {
+    /// No precommit-rs script was installed.
+    NotInstalled,
+    /// The hook exists but was not written by precommit-rs, so it was kept.
+    NotOurs(PathBuf),
+    /// Our script was removed.
+    Removed(PathBuf),
+    /// Our script was removed and the legacy hook moved back in its place.
+    Restored(PathBuf),
+}
+
+/// Remove the precommit-rs script for `hook_type` from `hooks_dir` and put
+/// back the hook it replaced, if any.
+pub fn remove_hook(hooks_dir: &Path, hook_type: HookType) -> Result<Removal> {
+    let hook_path = hooks_dir.join(hook_type.as_str());
+    if !hook_path.exists() {
+        return Ok(Removal::NotInstalled);
+    }
+    if !is_ours(&hook_path) {
+        return Ok(Removal::NotOurs(hook_path));
+    }
+
+    fs::remove_file(&hook_path)
+        .with_context(|| format!("Failed to remove {}", hook_path.display()))?;
+    let legacy = legacy_path(&hook_path);
+    if legacy.exists() {
+        fs::rename(&legacy, &hook_path)
+            .with_context(|| format!("Failed to restore {}", legacy.display()))?;
+        return Ok(Removal::Restored(hook_path));
+    }
+    Ok(Removal::Removed(hook_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_support::{git, init_repo};
+    use tempfile::tempdir;
+
+    #[test]
+    fn scripts_forward_stage_and_arguments() {
+        let script = hook_script(HookType::CommitMsg, "/usr/bin/precommit-rs", false);
+        assert!(script.starts_with("#!/usr/bin/env bash\n"));
+        assert!(script.contains(
+            "exec \"/usr/bin/precommit-rs\" run-config --hook-stage commit-msg -- \"$@\"\n"
+        ));
+    }
+
+    #[test]
+    fn writes_one_executable_script_per_type() {
+        let dir = tempdir().unwrap();
+        let hooks_dir = dir.path().join("hooks");
+        for hook_type in [HookType::PreCommit, HookType::PrePush] {
+            write_hook(&hooks_dir, hook_type, "precommit-rs", false).unwrap();
+        }
+        let pre_push = fs::read_to_string(hooks_dir.join("pre-push")).unwrap();
+        assert!(pre_push.contains("--hook-stage pre-push"));
+        assert!(hooks_dir.join("pre-commit").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(hooks_dir.join("pre-push"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+    }
+
+    #[test]
+    fn install_keeps_foreign_hook_and_uninstall_restores_it() {
+        let dir = tempdir().unwrap();
+        let hook = dir.path().join("pre-commit");
+        fs::write(&hook, "#!/bin/sh\necho mine\n").unwrap();
+
+        write_hook(dir.path(), HookType::PreCommit, "precommit-rs", false).unwrap();
+        assert!(is_ours(&hook));
+        let legacy = dir.path().join("pre-commit.legacy");
+        assert_eq!(
+            fs::read_to_string(&legacy).unwrap(),
+            "#!/bin/sh\necho mine\n"
+        );
+
+        // Re-installing replaces our own script without touching the legacy hook
+        write_hook(dir.path(), HookType::PreCommit, "precommit-rs", false).unwrap();
+        assert!(legacy.exists());
+
+        assert_eq!(
+            remove_hook(dir.path(), HookType::PreCommit).unwrap(),
+            Removal::Restored(hook.clone())
+        );
+        assert_eq!(fs::read_to_string(&hook).unwrap(), "#!/bin/sh\necho mine\n");
+        assert!(!legacy.exists());
+
+        // A foreign hook is never removed
+        assert_eq!(
+            remove_hook(dir.path(), HookType::PreCommit).unwrap(),
+            Removal::NotOurs(hook)
+        );
+        assert_eq!(
+            remove_hook(dir.path(), HookType::PrePush).unwrap(),
+            Removal::NotInstalled
+        );
+    }
+
+    #[test]
+    fn recognises_scripts_from_older_installs() {
+        let dir = tempdir().unwrap();
+        let hook = dir.path().join("pre-commit");
+        fs::write(
+            &hook,
+            "#!/usr/bin/env bash\nset -e\n\n# Run pre-commit hooks using /bin/p\nexec \"/bin/p\" run-config\n",
+        )
+        .unwrap();
+        assert!(is_ours(&hook));
+        assert_eq!(
+            remove_hook(dir.path(), HookType::PreCommit).unwrap(),
+            Removal::Removed(hook)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn chained_legacy_hook_runs_first_with_stdin() {
+        let dir = tempdir().unwrap();
+        let out = dir.path().join("legacy-ran");
+        fs::write(
+            dir.path().join("pre-push"),
+            format!("#!/bin/sh\ncat > '{}'\n", out.display()),
+        )
+        .unwrap();
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let hook = dir.path().join("pre-push");
+            let mut perms = fs::metadata(&hook).unwrap().permissions();
+            perms.set_mode(0o755);
+            fs::set_permissions(&hook, perms).unwrap();
+        }
+
+        // `true` stands in for the precommit-rs binary
+        let hook = write_hook(dir.path(), HookType::PrePush, "true", true).unwrap();
+        let mut child = Command::new(&hook)
+            .args(["origin", "url"])
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        {
+            use std::io::Write;
+            let mut stdin = child.stdin.take().unwrap();
+            stdin
+                .write_all(b"refs/heads/main a refs/heads/main b\n")
+                .unwrap();
+        }
+        assert!(child.wait().unwrap().success());
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "refs/heads/main a refs/heads/main b\n"
+        );
+    }
+
+    fn commit_repo(dir: &Path) {
+        init_repo(dir);
+        fs::write(dir.join("a.txt"), "a\n").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "init"]);
+    }
+
+    #[test]
+    fn linked_worktrees_share_the_main_hooks_dir() {
+        let dir = tempdir().unwrap();
+        let main = dir.path().join("main");
+        fs::create_dir(&main).unwrap();
+        commit_repo(&main);
+        git(&main, &["worktree", "add", "-q", "../wt"]);
+
+        let wt = dir.path().join("wt");
+        assert!(wt.join(".git").is_file());
+        assert_eq!(
+            normalize(&hooks_dir(&wt, false).unwrap()),
+            normalize(&main.join(".git/hooks"))
+        );
+    }
+
+    #[test]
+    fn submodules_use_their_own_git_dir() {
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        let sup = dir.path().join("super");
+        fs::create_dir(&sub).unwrap();
+        fs::create_dir(&sup).unwrap();
+        commit_repo(&sub);
+        commit_repo(&sup);
+        git(
+            &sup,
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                "-q",
+                sub.to_str().unwrap(),
+                "sub",
+            ],
+        );
+
+        assert_eq!(
+            normalize(&hooks_dir(&sup.join("sub"), false).unwrap()),
+            normalize(&sup.join(".git/modules/sub/hooks"))
+        );
+    }
+
+    #[test]
+    fn hooks_path_outside_the_repo_needs_force() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir(&repo).unwrap();
+        init_repo(&repo);
+
+        git(&repo, &["config", "core.hooksPath", ".githooks"]);
+        assert_eq!(
+            normalize(&hooks_dir(&repo, false).unwrap()),
+            normalize(&repo.join(".githooks"))
+        );
+
+        let shared = dir.path().join("shared-hooks");
+        git(
+            &repo,
+            &["config", "core.hooksPath", shared.to_str().unwrap()],
+        );
+        let err = hooks_dir(&repo, false).unwrap_err().to_string();
+        assert!(err.contains("outside this repository"), "{}", err);
+        assert_eq!(
+            normalize(&hooks_dir(&repo, true).unwrap()),
+            normalize(&shared)
+        );
+    }
+}
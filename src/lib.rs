@@ -1,18 +1,38 @@
+pub mod batch;
 pub mod changelog;
 pub mod cli;
+pub mod compat;
 pub mod config;
+pub mod file_index;
+pub mod git;
 pub mod hooks;
+pub mod identify;
+pub mod install;
 pub mod lock;
+pub mod parallel;
+pub mod pattern;
+pub mod process;
+pub mod summary;
 pub mod validate;
+pub mod validate_config;
 
 use changelog::Changelog;
+use hooks::HookRegistry;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct RunContext {
     pub dry_run: bool,
     pub debug: bool,
     pub changelog: Arc<Mutex<Changelog>>,
+    /// Hooks that config entries without a `command` are resolved against.
+    pub registry: Arc<HookRegistry>,
+    /// Threads used for the file batches of a hook and for files inside the
+    /// built-in hooks. Hooks themselves always run one after another.
+    pub jobs: usize,
+    /// Time limit for external hook commands without a `timeout` of their own.
+    pub timeout: Option<Duration>,
 }
 
 impl Default for RunContext {
@@ -21,6 +41,9 @@ impl Default for RunContext {
             dry_run: false,
             debug: false,
             changelog: Arc::new(Mutex::new(Changelog::new())),
+            registry: Arc::new(HookRegistry::builtin()),
+            jobs: parallel::default_jobs(),
+            timeout: None,
         }
     }
 }
@@ -40,14 +63,16 @@ mod tests {
         // Create RunContext with debug enabled
         let ctx = RunContext {
             debug: true,
-            dry_run: true, // Use dry_run mode to prevent exit(1)
+            dry_run: true,
             ..Default::default()
         };
 
         // Run the end-of-file-fixer hook
         let paths = vec![file.clone()];
-        let result = hooks::end_of_file::run_with_ctx(&ctx, paths);
-        assert!(result.is_ok());
+        let result = hooks::end_of_file::run_with_ctx(&ctx, paths).unwrap();
+        // A dry run passes and leaves the file alone
+        assert_eq!(result, hooks::HookOutcome::Passed);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "test\n\n\n");
 
         // Verify changelog contains the changes
         let changelog = ctx.changelog.lock().unwrap();
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs::{self, File};
@@ -29,8 +29,7 @@ impl Default for LockFile {
     fn default() -> Self {
         LockFile {
             version: 1,
-            generated_at: DateTime::<Utc>::from(Utc::now())
-                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            generated_at: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
             hooks: Vec::new(),
         }
     }
@@ -40,8 +39,7 @@ fn load_lock(path: &Path) -> Result<LockFile> {
     if path.exists() {
         let data = fs::read(path)?;
         let mut lock: LockFile = serde_yaml::from_slice(&data)?;
-        lock.generated_at =
-            DateTime::<Utc>::from(Utc::now()).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        lock.generated_at = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
         Ok(lock)
     } else {
         Ok(LockFile::default())
@@ -54,7 +52,8 @@ fn save_lock(path: &Path, lock: &LockFile) -> Result<()> {
     Ok(())
 }
 
-fn sha256_file(path: &Path) -> Result<String> {
+/// Hex SHA-256 digest of the file at `path`.
+pub(crate) fn sha256_file(path: &Path) -> Result<String> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
     let mut hasher = Sha256::new();
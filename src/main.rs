@@ -1,9 +1,16 @@
 use anyhow::anyhow;
 use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
-use std::{fs::File, io, path::PathBuf};
+use std::{
+    fs::File,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use precommit_rs::{cli, config, hooks, RunContext};
+use precommit_rs::{
+    cli, config, git, hooks, hooks::HookOutcome, install, validate_config, RunContext,
+};
 
 const COLOR_RESET: &str = "\x1b[0m";
 const COLOR_REPO: &str = "\x1b[1;34m";
@@ -42,6 +49,14 @@ struct Cli {
     #[arg(long, global = true)]
     debug: bool,
 
+    /// Number of parallel jobs for file batches and built-in hooks (default: number of CPUs)
+    #[arg(long, short = 'j', global = true, value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: Option<u32>,
+
+    /// Seconds an external hook command may run before it is killed (default: no limit)
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -72,22 +87,78 @@ enum Commands {
     },
     /// List hooks from configuration
     ListHooks {
-        /// Path to configuration file (default: .pre-commit.yaml)
+        /// Path to configuration file (default: .pre-commit.yaml or .pre-commit-config.yaml)
         #[arg(long)]
         config: Option<PathBuf>,
         /// Include disabled hooks in the output
         #[arg(long)]
         all: bool,
     },
+    /// Check a config file for unknown keys and invalid values without running hooks
+    ValidateConfig {
+        /// Path to configuration file (default: .pre-commit.yaml or .pre-commit-config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+    /// Print the JSON Schema of the config file, for editor validation and completion
+    Schema,
     /// Read a pre-commit YAML config file and run the enabled hooks
-    RunConfig { config: Option<PathBuf> },
+    RunConfig {
+        /// Hooks to run, by id (default: every hook)
+        hook_ids: Vec<String>,
+        /// Path to configuration file (default: .pre-commit.yaml or .pre-commit-config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Hook to run, by id (repeatable; same as giving the id as an argument)
+        #[arg(long = "hook")]
+        hooks: Vec<String>,
+        /// Run on every file in the repository instead of only staged files
+        #[arg(long)]
+        all_files: bool,
+        /// Run on these files instead of the staged files
+        #[arg(long, num_args = 1.., conflicts_with = "all_files")]
+        files: Vec<PathBuf>,
+        /// Run on the files changed between this commit and --to-ref
+        #[arg(long, requires = "to_ref", conflicts_with_all = ["all_files", "files"])]
+        from_ref: Option<String>,
+        /// Run on the files changed between --from-ref and this commit
+        #[arg(long, requires = "from_ref")]
+        to_ref: Option<String>,
+        /// Only run hooks configured for this git hook stage
+        #[arg(long, value_enum, default_value = "pre-commit")]
+        hook_stage: config::Stage,
+        /// Show the output of every hook, not just failing ones
+        #[arg(long)]
+        verbose: bool,
+        /// Arguments git passed to the hook script (after `--`)
+        #[arg(last = true)]
+        hook_args: Vec<String>,
+    },
     /// Create a default .pre-commit.yaml in the current directory (or specified path)
     Init { path: Option<PathBuf> },
-    /// Install a git pre-commit hook in the repository that runs precommit-rs
+    /// Install git hooks in the repository that run precommit-rs
     Install {
         /// Path to the precommit-rs binary to use (optional)
         #[arg(long)]
         path: Option<String>,
+        /// Git hook to install (repeatable; default: default_install_hook_types or pre-commit)
+        #[arg(long = "hook-type", value_enum)]
+        hook_types: Vec<config::HookType>,
+        /// Run a hook that existed before install (saved as <hook>.legacy) before precommit-rs
+        #[arg(long)]
+        chain_legacy: bool,
+        /// Install even if core.hooksPath points outside the repository
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove git hooks written by install and restore the hooks they replaced
+    Uninstall {
+        /// Git hook to remove (repeatable; default: every hook type)
+        #[arg(long = "hook-type", value_enum)]
+        hook_types: Vec<config::HookType>,
+        /// Uninstall even if core.hooksPath points outside the repository
+        #[arg(long)]
+        force: bool,
     },
     /// Create a new custom pre-commit hook from a template
     CreateHook {
@@ -109,18 +180,64 @@ enum Commands {
     },
 }
 
+/// Load `path`, or the config file in the current directory, and print a
+/// warning for everything in it that precommit-rs ignores.
+fn load_config(path: Option<&Path>) -> anyhow::Result<config::PreCommitConfig> {
+    let conf = config::PreCommitConfig::from_file(path.unwrap_or(Path::new(".")))?;
+    print_warnings(&conf);
+    Ok(conf)
+}
+
+fn print_warnings(conf: &config::PreCommitConfig) {
+    for warning in conf.warnings() {
+        eprintln!("Warning: {}: {}", conf.path().display(), warning);
+    }
+}
+
+/// Validate `path`, or the config file in the current directory, printing
+/// every problem found and the config's warnings. Returns the config if it
+/// is valid.
+fn check_config(path: Option<&Path>) -> anyhow::Result<Option<config::PreCommitConfig>> {
+    let (problems, conf) = validate_config::validate_file(path.unwrap_or(Path::new(".")))?;
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    if !problems.iter().all(|problem| problem.warning) {
+        return Ok(None);
+    }
+    if let Some(conf) = &conf {
+        print_warnings(conf);
+    }
+    Ok(conf)
+}
+
+/// Print a hook's diagnostics and exit with its status code.
+fn exit_with(outcome: HookOutcome) -> ! {
+    for message in outcome.diagnostics() {
+        eprintln!("{}", message);
+    }
+    std::process::exit(outcome.exit_code());
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
-    let mut ctx = RunContext::default();
-    ctx.dry_run = cli.dry_run;
-    ctx.debug = cli.debug;
+    let ctx = RunContext {
+        dry_run: cli.dry_run,
+        debug: cli.debug,
+        jobs: cli
+            .jobs
+            .map(|jobs| jobs as usize)
+            .unwrap_or_else(precommit_rs::parallel::default_jobs),
+        timeout: cli.timeout.map(Duration::from_secs),
+        ..Default::default()
+    };
 
     match cli.command {
-        Commands::TrailingWhitespace { paths } => hooks::trailing_whitespace::run_with_ctx(&ctx, paths),
-        Commands::EndOfFileFixer { paths } => hooks::end_of_file::run_with_ctx(&ctx, paths),
-        Commands::CheckAddedLargeFiles { max_bytes, paths } => hooks::check_added_large_files::run_with_ctx(&ctx, max_bytes, paths),
-        Commands::CheckYaml { paths } => hooks::check_yaml::run_with_ctx(&ctx, paths),
-        Commands::PrettyFormatJson { paths } => hooks::pretty_format_json::run_with_ctx(&ctx, paths),
+        Commands::TrailingWhitespace { paths } => exit_with(hooks::trailing_whitespace::run_with_ctx(&ctx, paths)?),
+        Commands::EndOfFileFixer { paths } => exit_with(hooks::end_of_file::run_with_ctx(&ctx, paths)?),
+        Commands::CheckAddedLargeFiles { max_bytes, paths } => exit_with(hooks::check_added_large_files::run_with_ctx(&ctx, max_bytes, paths)?),
+        Commands::CheckYaml { paths } => exit_with(hooks::check_yaml::run_with_ctx(&ctx, paths)?),
+        Commands::PrettyFormatJson { paths } => exit_with(hooks::pretty_format_json::run_with_ctx(&ctx, paths)?),
         Commands::Completions { shell, out } => {
             let mut cmd = Cli::command();
             let bin_name = cmd.get_name().to_string();
@@ -130,7 +247,7 @@ fn main() -> anyhow::Result<()> {
                 clap_complete::generate(shell, &mut cmd, bin_name, &mut file);
                 println!(
                     "Wrote {} completions to {}",
-                    shell.to_string(),
+                    shell,
                     path.display()
                 );
             } else {
@@ -139,18 +256,74 @@ fn main() -> anyhow::Result<()> {
             }
             Ok(())
         }
-        Commands::RunConfig { config } => {
-            let cfg_path = config.unwrap_or_else(|| PathBuf::from(".pre-commit.yaml"));
-            let conf = config::PreCommitConfig::from_file(&cfg_path)?;
+        Commands::RunConfig {
+            mut hook_ids,
+            config,
+            hooks,
+            all_files,
+            files,
+            from_ref,
+            to_ref,
+            hook_stage,
+            verbose,
+            hook_args,
+        } => {
+            let Some(conf) = check_config(config.as_deref())? else {
+                std::process::exit(1);
+            };
             if ctx.debug {
-                eprintln!("Loaded config from {}: {:#?}", cfg_path.display(), conf);
+                eprintln!("Loaded config from {}: {:#?}", conf.path().display(), conf);
+            }
+            let cwd = std::env::current_dir()?;
+            let root = config::run_root(&cwd);
+            let opts = config::RunOptions {
+                files: if all_files {
+                    config::FileSelection::AllFiles
+                } else if !files.is_empty() {
+                    config::FileSelection::files(&files, &cwd, &root)?
+                } else if let (Some(from), Some(to)) = (&from_ref, &to_ref) {
+                    config::FileSelection::changed_between(from, to)?
+                } else {
+                    let stdin = io::stdin();
+                    let stdin = (!stdin.is_terminal()).then(|| stdin.lock());
+                    config::FileSelection::for_stage(hook_stage, &hook_args, stdin)?
+                },
+                stage: hook_stage,
+                verbose,
+                hook_ids: {
+                    hook_ids.extend(hooks);
+                    hook_ids
+                },
+                skip: std::env::var("SKIP")
+                    .map(|skip| config::parse_skip(&skip))
+                    .unwrap_or_default(),
+            };
+            // Hooks run from the root, like upstream's, and see its paths
+            std::env::set_current_dir(&root)?;
+            let summary = config::run_config(&ctx, &conf, &opts)?;
+            print!("{}", summary.render(io::stdout().is_terminal()));
+            if !summary.is_success() {
+                std::process::exit(summary.exit_code());
             }
-            config::run_config(&ctx, &conf)?;
+            Ok(())
+        }
+        Commands::ValidateConfig { config } => {
+            let Some(conf) = check_config(config.as_deref())? else {
+                std::process::exit(1);
+            };
+            println!("{} is valid", conf.path().display());
+            Ok(())
+        }
+        Commands::Schema => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&config::config_schema())?
+            );
             Ok(())
         }
         Commands::ListHooks { config, all } => {
-            let cfg_path = config.unwrap_or_else(|| PathBuf::from(".pre-commit.yaml"));
-            let conf = config::PreCommitConfig::from_file(&cfg_path)?;
+            let conf = load_config(config.as_deref())?;
+            let cfg_path = conf.path();
             let repos = conf.repos();
 
             if repos.is_empty() {
@@ -191,7 +364,7 @@ fn main() -> anyhow::Result<()> {
                         (
                             COLOR_HOOK_ENABLED,
                             format!("{}{status}{}", COLOR_STATUS_ENABLED, COLOR_RESET, status = "enabled"),
-                            if hook.is_builtin() {
+                            if hook.is_builtin(&ctx.registry) {
                                 format!("{}{kind}{}", COLOR_KIND_BUILTIN, COLOR_RESET, kind = "builtin")
                             } else {
                                 format!("{}{kind}{}", COLOR_KIND_EXTERNAL, COLOR_RESET, kind = "external")
@@ -201,7 +374,7 @@ fn main() -> anyhow::Result<()> {
                         (
                             COLOR_HOOK_DISABLED,
                             format!("{}{status}{}", COLOR_STATUS_DISABLED, COLOR_RESET, status = "disabled"),
-                            if hook.is_builtin() {
+                            if hook.is_builtin(&ctx.registry) {
                                 format!("{}{kind}{}", COLOR_KIND_BUILTIN, COLOR_RESET, kind = "builtin")
                             } else {
                                 format!("{}{kind}{}", COLOR_KIND_EXTERNAL, COLOR_RESET, kind = "external")
@@ -239,7 +412,15 @@ fn main() -> anyhow::Result<()> {
                     let stages_note = hook
                         .stages()
                         .filter(|s| !s.is_empty())
-                        .map(|s| format!(" {}[stages: {}]{}", COLOR_NOTE, s.join(","), COLOR_RESET))
+                        .map(|s| {
+                            let names: Vec<&str> = s.iter().map(|stage| stage.as_str()).collect();
+                            format!(
+                                " {}[stages: {}]{}",
+                                COLOR_NOTE,
+                                names.join(","),
+                                COLOR_RESET
+                            )
+                        })
                         .unwrap_or_default();
                     let deps_note = hook
                         .additional_dependencies()
@@ -249,13 +430,15 @@ fn main() -> anyhow::Result<()> {
 
                     if let Some(cmd) = hook.command() {
                         println!(
-                            "  - {}{}{} ({}, {}) -> {}{}{}{}{}{}{}",
+                            "  - {}{}{} ({}, {}) -> {}{}{}{}{}{}{}{}{}",
                             id_color,
                             hook.id(),
                             COLOR_RESET,
                             status_label,
                             kind_label,
-                            format!("{}{cmd}{}", COLOR_COMMAND, COLOR_RESET, cmd = cmd),
+                            COLOR_COMMAND,
+                            cmd,
+                            COLOR_RESET,
                             hook
                                 .args()
                                 .map(|args| format!(" {}{}{}", COLOR_COMMAND, args.join(" "), COLOR_RESET))
@@ -296,15 +479,14 @@ fn main() -> anyhow::Result<()> {
             println!("Wrote default config to {}", p.display());
             Ok(())
         }
-        Commands::ValidateHook { hook_name } => {
-            match hook_name.as_str() {
-                "end-of-file-fixer" => precommit_rs::validate::validate_hook("end-of-file-fixer", hooks::end_of_file::run_with_ctx),
-                "trailing-whitespace" => precommit_rs::validate::validate_hook("trailing-whitespace", hooks::trailing_whitespace::run_with_ctx),
-                "check-yaml" => precommit_rs::validate::validate_hook("check-yaml", hooks::check_yaml::run_with_ctx),
-                "pretty-format-json" => precommit_rs::validate::validate_hook("pretty-format-json", hooks::pretty_format_json::run_with_ctx),
-                _ => Err(anyhow!("Unknown hook: {}. Available hooks: end-of-file-fixer, trailing-whitespace, check-yaml, pretty-format-json", hook_name)),
-            }
-        }
+        Commands::ValidateHook { hook_name } => match ctx.registry.get(&hook_name) {
+            Some(hook) => precommit_rs::validate::validate_hook(hook),
+            None => Err(anyhow!(
+                "Unknown hook: {}. Available hooks: {}",
+                hook_name,
+                ctx.registry.ids().join(", ")
+            )),
+        },
         Commands::CreateHook { name, language, description, output_dir } => {
             let output_dir = output_dir.unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
             let hook_dir = output_dir.join(&name);
@@ -385,69 +567,80 @@ fn main() -> anyhow::Result<()> {
             println!("For Rust hooks, run 'cargo build --release' in the hook directory before using");
             Ok(())
         }
-        Commands::Install { path } => {
-            // Find repo root
-            let root_out = std::process::Command::new("git").args(["rev-parse", "--show-toplevel"]).output()?;
-            let repo_root = String::from_utf8_lossy(&root_out.stdout).trim().to_string();
-            let hook_path = PathBuf::from(&repo_root).join(".git/hooks/pre-commit");
-
-            // Determine binary path:
-            // 1. Use --path if provided
-            // 2. Try `which precommit-rs`
-            // 3. Fall back to ./target/release/precommit-rs
-            let binary_path = if let Some(p) = path {
-                p
+        Commands::Uninstall { hook_types, force } => {
+            let hooks_dir = install::hooks_dir(&std::env::current_dir()?, force)?;
+            let hook_types = if hook_types.is_empty() {
+                config::HookType::value_variants().to_vec()
             } else {
-                // Try to find installed binary with `which`
-                let which_out = std::process::Command::new("which")
-                    .arg("precommit-rs")
-                    .output();
-
-                match which_out {
-                    Ok(out) if out.status.success() => {
-                        String::from_utf8_lossy(&out.stdout).trim().to_string()
-                    }
-                    _ => {
-                        // Fall back to local release binary
-                        let local_bin = format!("{}/target/release/precommit-rs", repo_root);
+                hook_types
+            };
+            for hook_type in hook_types {
+                match install::remove_hook(&hooks_dir, hook_type)? {
+                    install::Removal::NotInstalled => {
                         if ctx.debug {
-                            eprintln!("No installed binary found, using {}", local_bin);
+                            eprintln!("No {} hook installed", hook_type.as_str());
                         }
-                        local_bin
+                    }
+                    install::Removal::NotOurs(path) => println!(
+                        "Leaving {} in place: it was not installed by precommit-rs",
+                        path.display()
+                    ),
+                    install::Removal::Removed(path) => {
+                        println!("Removed git hook {}", path.display())
+                    }
+                    install::Removal::Restored(path) => {
+                        println!("Removed git hook and restored previous {}", path.display())
                     }
                 }
+            }
+            Ok(())
+        }
+        Commands::Install {
+            path,
+            hook_types,
+            chain_legacy,
+            force,
+        } => {
+            let cwd = std::env::current_dir()?;
+            let repo_root = git::repo_root(&cwd)?;
+            let hooks_dir = install::hooks_dir(&cwd, force)?;
+
+            let cfg_path = config::find_config(&repo_root);
+            let conf = cfg_path
+                .as_deref()
+                .map(|path| load_config(Some(path)))
+                .transpose()?;
+
+            let hook_types = if !hook_types.is_empty() {
+                hook_types
+            } else {
+                conf.as_ref()
+                    .and_then(|c| c.default_install_hook_types())
+                    .map(|types| types.to_vec())
+                    .unwrap_or_else(|| vec![config::HookType::PreCommit])
             };
 
-            let script = format!(
-                "#!/usr/bin/env bash\n\
-                set -e\n\
-                \n\
-                # Run pre-commit hooks using {}\n\
-                exec \"{}\" run-config\n",
-                binary_path, binary_path
-            );
-
+            let binary_path = install::resolve_binary(path, &repo_root, ctx.debug);
             if ctx.debug {
-                eprintln!("Writing hook script to use binary: {}", binary_path);
+                eprintln!("Writing hook scripts to use binary: {}", binary_path);
             }
-            std::fs::write(&hook_path, script)?;
-
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                let mut perms = std::fs::metadata(&hook_path)?.permissions();
-                perms.set_mode(0o755);
-                std::fs::set_permissions(&hook_path, perms)?;
+            for hook_type in hook_types {
+                let hook_path =
+                    install::write_hook(&hooks_dir, hook_type, &binary_path, chain_legacy)?;
+                println!(
+                    "Installed git hook at {} using binary: {}",
+                    hook_path.display(),
+                    binary_path
+                );
             }
 
-            println!("Installed git hook at {} using binary: {}", hook_path.display(), binary_path);
-
-            let cfg_path = PathBuf::from(&repo_root).join(".pre-commit.yaml");
-            if cfg_path.exists() {
+            if let Some(conf) = conf {
                 if ctx.debug {
-                    eprintln!("Ensuring external hooks are installed per {}", cfg_path.display());
+                    eprintln!(
+                        "Ensuring external hooks are installed per {}",
+                        conf.path().display()
+                    );
                 }
-                let conf = config::PreCommitConfig::from_file(&cfg_path)?;
                 for (_, hook) in conf.local_hooks() {
                     if hook.command_is_install() {
                         if ctx.debug {
@@ -458,7 +651,7 @@ fn main() -> anyhow::Result<()> {
                 }
                 println!("Updated .precommit-lock.yaml with installed hook hashes.");
             } else if ctx.debug {
-                eprintln!("No .pre-commit.yaml found at {}", cfg_path.display());
+                eprintln!("No config file found in {}", repo_root.display());
             }
             Ok(())
         }
@@ -0,0 +1,410 @@
+use anyhow::{anyhow, bail, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::path::Path;
+
+/// How `files` and `exclude` patterns in the config are interpreted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternSyntax {
+    /// Shell-style globs such as `**/*.{yml,yaml}`.
+    #[default]
+    Glob,
+    /// Regular expressions searched for in the repo-relative path, as in
+    /// upstream pre-commit's `.pre-commit-config.yaml`.
+    Regex,
+}
+
+/// A `files` or `exclude` value: one pattern or a list of them. Entries
+/// starting with `!` remove files matched by the other entries.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Patterns {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Patterns {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            Patterns::One(pattern) => std::slice::from_ref(pattern),
+            Patterns::Many(patterns) => patterns,
+        }
+    }
+}
+
+impl std::fmt::Display for Patterns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_slice().join(", "))
+    }
+}
+
+/// Upper bound on the patterns one entry may expand to, so a typo such as
+/// a long run of `{a,b}` groups fails instead of exhausting memory.
+const MAX_EXPANSIONS: usize = 4096;
+
+/// Compiled form of one side (positive or negated) of a pattern list.
+#[derive(Debug, Clone)]
+enum PatternSet {
+    Glob(GlobSet),
+    Regex(regex::RegexSet),
+}
+
+impl PatternSet {
+    fn new(patterns: &[&str], syntax: PatternSyntax) -> Result<Option<Self>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(match syntax {
+            PatternSyntax::Glob => {
+                let mut builder = GlobSetBuilder::new();
+                for pattern in patterns {
+                    for expanded in expand_braces(pattern)? {
+                        let glob = GlobBuilder::new(&expanded)
+                            .backslash_escape(true)
+                            .build()
+                            .map_err(|e| anyhow!("Invalid glob pattern '{}': {}", pattern, e))?;
+                        builder.add(glob);
+                    }
+                }
+                PatternSet::Glob(builder.build()?)
+            }
+            PatternSyntax::Regex => {
+                for pattern in patterns {
+                    compile_regex(pattern)?;
+                }
+                PatternSet::Regex(regex::RegexSet::new(patterns)?)
+            }
+        }))
+    }
+
+    fn is_match(&self, relative: &str) -> bool {
+        match self {
+            PatternSet::Glob(set) => set.is_match(relative),
+            PatternSet::Regex(set) => set.is_match(relative),
+        }
+    }
+}
+
+/// Compiled `files` or `exclude` patterns.
+#[derive(Debug, Clone)]
+pub struct FileMatcher {
+    /// `None` when every entry is a negation: everything else matches.
+    include: Option<PatternSet>,
+    negated: Option<PatternSet>,
+}
+
+impl FileMatcher {
+    pub fn new(patterns: &[String], syntax: PatternSyntax) -> Result<Self> {
+        let (negated, include): (Vec<&str>, Vec<&str>) = patterns
+            .iter()
+            .map(String::as_str)
+            .partition(|p| p.starts_with('!'));
+        let negated: Vec<&str> = negated.iter().map(|p| &p[1..]).collect();
+        Ok(Self {
+            include: PatternSet::new(&include, syntax)?,
+            negated: PatternSet::new(&negated, syntax)?,
+        })
+    }
+
+    /// Whether a file matches. Globs and regexes both see only the relative
+    /// path, given `/` separators and no leading `./`, so where the checkout
+    /// lives never decides what a pattern matches.
+    pub fn is_match(&self, relative: &Path) -> bool {
+        let relative = normalize_relative(relative);
+        self.include
+            .as_ref()
+            .is_none_or(|set| set.is_match(&relative))
+            && !self
+                .negated
+                .as_ref()
+                .is_some_and(|set| set.is_match(&relative))
+    }
+}
+
+fn normalize_relative(relative: &Path) -> String {
+    let path = relative.to_string_lossy().replace('\\', "/");
+    match path.strip_prefix("./") {
+        Some(stripped) => stripped.to_string(),
+        None => path,
+    }
+}
+
+/// Index of the `}` closing the `{` at `open`, skipping escaped characters
+/// and nested groups.
+fn closing_brace(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Split the inside of a brace group at its top-level commas.
+fn split_alternatives(chars: &[char]) -> Vec<String> {
+    let mut alternatives = vec![String::new()];
+    let mut depth = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                let current = alternatives.last_mut().unwrap();
+                current.push(c);
+                if let Some(next) = chars.get(i + 1) {
+                    current.push(*next);
+                }
+                i += 2;
+                continue;
+            }
+            ',' if depth == 0 => {
+                alternatives.push(String::new());
+                i += 1;
+                continue;
+            }
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        alternatives.last_mut().unwrap().push(c);
+        i += 1;
+    }
+    alternatives
+}
+
+/// Expand every `{a,b}` group in a glob, including nested groups and several
+/// groups in one pattern: `{src,lib}/*.{rs,toml}` gives four patterns.
+/// `\{`, `\}` and `\,` are literal and left escaped for the glob compiler.
+pub fn expand_braces(pattern: &str) -> Result<Vec<String>> {
+    let mut done = Vec::new();
+    let mut pending = vec![pattern.to_string()];
+    while let Some(current) = pending.pop() {
+        let chars: Vec<char> = current.chars().collect();
+        let mut open = None;
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' => i += 1,
+                '{' => {
+                    open = Some(i);
+                    break;
+                }
+                '}' => bail!("Unmatched '}}' in pattern '{}'", pattern),
+                _ => {}
+            }
+            i += 1;
+        }
+
+        let Some(open) = open else {
+            done.push(current);
+            continue;
+        };
+        let close = closing_brace(&chars, open)
+            .ok_or_else(|| anyhow!("Unmatched '{{' in pattern '{}'", pattern))?;
+        let before: String = chars[..open].iter().collect();
+        let after: String = chars[close + 1..].iter().collect();
+        // Push in reverse so expansions come out in the written order
+        for alternative in split_alternatives(&chars[open + 1..close]).iter().rev() {
+            pending.push(format!("{}{}{}", before, alternative, after));
+        }
+        if done.len() + pending.len() > MAX_EXPANSIONS {
+            bail!(
+                "Pattern '{}' expands to more than {} alternatives",
+                pattern,
+                MAX_EXPANSIONS
+            );
+        }
+    }
+    Ok(done)
+}
+
+fn compile_regex(pattern: &str) -> Result<regex::Regex> {
+    if let Some(construct) = unsupported_construct(pattern) {
+        bail!(
+            "Unsupported regex in '{}': {} (pattern_syntax: regex uses the Rust regex crate, which has no lookaround or backreferences)",
+            pattern,
+            construct
+        );
+    }
+    regex::Regex::new(pattern).map_err(|e| anyhow!("Invalid regex '{}': {}", pattern, e))
+}
+
+/// Describe the first Python `re` feature in `pattern` that the regex crate
+/// does not support, so ported configs fail with a useful message instead of
+/// a bare parse error.
+fn unsupported_construct(pattern: &str) -> Option<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut in_class = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let rest: String = chars[i..].iter().take(4).collect();
+        match chars[i] {
+            '\\' => {
+                match chars.get(i + 1) {
+                    Some(d @ '1'..='9') if !in_class => {
+                        return Some(format!("backreference '\\{}'", d));
+                    }
+                    Some('Z') if !in_class => {
+                        return Some("'\\Z' (use '\\z' for the end of the path)".to_string());
+                    }
+                    _ => {}
+                }
+                i += 2;
+                continue;
+            }
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '(' if !in_class => {
+                let construct = if rest.starts_with("(?=") {
+                    Some("lookahead '(?=...)'")
+                } else if rest.starts_with("(?!") {
+                    Some("negative lookahead '(?!...)'")
+                } else if rest.starts_with("(?<=") {
+                    Some("lookbehind '(?<=...)'")
+                } else if rest.starts_with("(?<!") {
+                    Some("negative lookbehind '(?<!...)'")
+                } else if rest.starts_with("(?P=") {
+                    Some("named backreference '(?P=name)'")
+                } else if rest.starts_with("(?>") {
+                    Some("atomic group '(?>...)'")
+                } else if rest.starts_with("(?(") {
+                    Some("conditional group '(?(...)...)'")
+                } else {
+                    None
+                };
+                if let Some(construct) = construct {
+                    return Some(construct.to_string());
+                }
+            }
+            '*' | '+' | '?' | '}' if !in_class && chars.get(i + 1) == Some(&'+') => {
+                return Some(format!("possessive quantifier '{}+'", chars[i]));
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, syntax: PatternSyntax, path: &str) -> bool {
+        matches_all(&[pattern], syntax, path)
+    }
+
+    fn matches_all(patterns: &[&str], syntax: PatternSyntax, path: &str) -> bool {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        let matcher = FileMatcher::new(&patterns, syntax).unwrap();
+        matcher.is_match(Path::new(path))
+    }
+
+    #[test]
+    fn regex_is_searched_in_repo_relative_paths() {
+        let re = PatternSyntax::Regex;
+        assert!(matches(r"\.py$", re, "src/app.py"));
+        assert!(matches(r"^docs/", re, "./docs/index.md"));
+        assert!(!matches(r"^docs/", re, "src/docs/index.md"));
+        assert!(matches(r"^(src|tests)/.*\.rs$", re, "tests/a.rs"));
+        // The same text as a glob means something else entirely
+        assert!(!matches(r"\.py$", PatternSyntax::Glob, "src/app.py"));
+    }
+
+    #[test]
+    fn python_only_constructs_are_reported() {
+        for (pattern, expected) in [
+            (r"^(?!vendor/).*\.py$", "negative lookahead"),
+            (r"(?<=src/)x", "lookbehind"),
+            (r"(a)\1", "backreference"),
+            (r"(?P<n>a)(?P=n)", "named backreference"),
+            (r"\.py\Z", r"'\z'"),
+            (r"a++", "possessive"),
+        ] {
+            let err = FileMatcher::new(&[pattern.to_string()], PatternSyntax::Regex)
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains(expected), "{}: {}", pattern, err);
+        }
+
+        // Escaped or bracketed look-alikes are fine
+        for pattern in [r"\(\?=", r"[(?=]", r"(?P<name>x)", r"(?i)readme"] {
+            assert!(
+                FileMatcher::new(&[pattern.to_string()], PatternSyntax::Regex).is_ok(),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn expands_every_brace_group() {
+        assert_eq!(
+            expand_braces("{src,lib}/**/*.{rs,toml}").unwrap(),
+            vec![
+                "src/**/*.rs",
+                "src/**/*.toml",
+                "lib/**/*.rs",
+                "lib/**/*.toml"
+            ]
+        );
+        assert_eq!(
+            expand_braces("*.{y{a,}ml,json}").unwrap(),
+            vec!["*.yaml", "*.yml", "*.json"]
+        );
+        assert_eq!(expand_braces("a{}b").unwrap(), vec!["ab"]);
+        assert_eq!(expand_braces("plain").unwrap(), vec!["plain"]);
+        // Escaped braces and commas are literal
+        assert_eq!(expand_braces(r"\{a,b\}").unwrap(), vec![r"\{a,b\}"]);
+        assert_eq!(expand_braces(r"{a\,b,c}").unwrap(), vec![r"a\,b", "c"]);
+    }
+
+    #[test]
+    fn rejects_unbalanced_and_exploding_braces() {
+        assert!(expand_braces("{a,b").is_err());
+        assert!(expand_braces("a,b}").is_err());
+        assert!(expand_braces(&"{a,b,c,d}".repeat(8)).is_err());
+    }
+
+    #[test]
+    fn glob_lists_with_negation() {
+        let glob = PatternSyntax::Glob;
+        let patterns = ["{src,lib}/**/*.{rs,toml}", "!**/generated/**"];
+        assert!(matches_all(&patterns, glob, "src/a/b.rs"));
+        assert!(matches_all(&patterns, glob, "./lib/Cargo.toml"));
+        assert!(!matches_all(&patterns, glob, "docs/a.rs"));
+        assert!(!matches_all(&patterns, glob, "src/generated/a.rs"));
+        // Only negations: everything else matches
+        assert!(matches_all(&["!*.lock"], glob, "src/a.rs"));
+        assert!(!matches_all(&["!*.lock"], glob, "Cargo.lock"));
+        assert!(matches(r"\{literal\}.txt", glob, "{literal}.txt"));
+    }
+
+    #[test]
+    fn negation_does_not_see_the_checkout_path() {
+        let dir = tempfile::Builder::new()
+            .prefix("generated-")
+            .tempdir()
+            .unwrap();
+        std::fs::write(dir.path().join("a.rs"), "").unwrap();
+        let index = crate::file_index::FileIndex::walk(dir.path()).unwrap();
+        let file = &index.files()[0];
+
+        let patterns = ["**/*.rs".to_string(), "!**/generated-*/**".to_string()];
+        let matcher = FileMatcher::new(&patterns, PatternSyntax::Glob).unwrap();
+        assert!(matcher.is_match(file.path()));
+    }
+}
@@ -0,0 +1,196 @@
+use std::io::{self, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest pause between checks on whether a command has exited.
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Captured result of a command run by `output`.
+#[derive(Debug)]
+pub struct Captured {
+    /// Exit status, or `None` when the command was killed at its deadline.
+    pub status: Option<ExitStatus>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl Captured {
+    pub fn timed_out(&self) -> bool {
+        self.status.is_none()
+    }
+}
+
+/// Run `command` with stdin closed and stdout and stderr captured, like
+/// `Command::output`, but kill it once `deadline` passes.
+///
+/// With a deadline the command runs in a process group of its own, so that
+/// everything it started (e.g. the tools a shell script runs) is killed with
+/// it. Without one it stays in ours and still receives Ctrl-C from the
+/// terminal.
+pub fn output(command: &mut Command, deadline: Option<Instant>) -> io::Result<Captured> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if deadline.is_some() {
+        new_process_group(command);
+    }
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        return Ok(Captured {
+            status: None,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        });
+    }
+
+    let mut child = command.spawn()?;
+    // Drain both pipes while waiting so a chatty command cannot block on a
+    // full pipe
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let mut status = match deadline {
+        None => Some(child.wait()?),
+        Some(deadline) => wait_until(&mut child, deadline)?,
+    };
+    if let Some(deadline) = deadline {
+        if status.is_some() && !finished_by([&stdout, &stderr], deadline) {
+            // Something the command started still holds the pipes open
+            kill_process_group(&mut child)?;
+            status = None;
+        }
+    }
+    Ok(Captured {
+        status,
+        stdout: stdout.map(join).unwrap_or_default(),
+        stderr: stderr.map(join).unwrap_or_default(),
+    })
+}
+
+fn drain(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        // A read error leaves what was read so far
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+fn join(handle: thread::JoinHandle<Vec<u8>>) -> Vec<u8> {
+    handle.join().unwrap_or_default()
+}
+
+/// Wait until the drain threads are done, which is when every process
+/// holding the pipes has exited, or `deadline` passes. Returns whether they
+/// finished.
+fn finished_by(handles: [&Option<thread::JoinHandle<Vec<u8>>>; 2], deadline: Instant) -> bool {
+    let mut interval = Duration::from_millis(1);
+    loop {
+        if handles.iter().copied().flatten().all(|h| h.is_finished()) {
+            return true;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        thread::sleep(interval.min(deadline - now));
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+/// Wait for `child` to exit, killing its process group at `deadline`.
+fn wait_until(child: &mut Child, deadline: Instant) -> io::Result<Option<ExitStatus>> {
+    let mut interval = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            kill_process_group(child)?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(interval.min(deadline - now));
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+fn new_process_group(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+#[cfg(not(unix))]
+fn new_process_group(_command: &mut Command) {}
+
+#[cfg(unix)]
+fn kill_process_group(child: &mut Child) -> io::Result<()> {
+    // The child leads its group, so the group id is its pid
+    let group = child.id() as libc::pid_t;
+    // SAFETY: killpg only sends a signal; the group id stays reserved while
+    // the child, its leader, is unreaped or any member is left
+    if unsafe { libc::killpg(group, libc::SIGKILL) } == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::ESRCH) {
+        // Exited between the last check and the kill
+        return Ok(());
+    }
+    Err(err)
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut Child) -> io::Result<()> {
+    child.kill()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_output_of_commands_that_finish() {
+        let captured = output(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+            Some(Instant::now() + Duration::from_secs(30)),
+        )
+        .unwrap();
+        assert_eq!(captured.status.unwrap().code(), Some(3));
+        assert_eq!(captured.stdout, b"out\n");
+        assert_eq!(captured.stderr, b"err\n");
+    }
+
+    #[test]
+    fn kills_the_whole_process_group_at_the_deadline() {
+        let start = Instant::now();
+        // The backgrounded sleep keeps stdout open, so the output would never
+        // end if only the shell were killed
+        let captured = output(
+            Command::new("sh").args(["-c", "echo started; sleep 30 & sleep 30"]),
+            Some(start + Duration::from_millis(200)),
+        )
+        .unwrap();
+        assert!(captured.timed_out());
+        assert_eq!(captured.stdout, b"started\n");
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn kills_what_outlives_the_command_at_the_deadline() {
+        let start = Instant::now();
+        // The shell exits at once, but the sleep it leaves behind keeps
+        // stdout open
+        let captured = output(
+            Command::new("sh").args(["-c", "echo started; sleep 30 &"]),
+            Some(start + Duration::from_millis(200)),
+        )
+        .unwrap();
+        assert!(captured.timed_out());
+        assert_eq!(captured.stdout, b"started\n");
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
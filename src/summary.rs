@@ -0,0 +1,271 @@
+use crate::hooks::HookOutcome;
+use std::fmt::Write;
+
+/// Total width of a summary line, matching upstream pre-commit.
+const LINE_WIDTH: usize = 79;
+
+const COLOR_RESET: &str = "\x1b[0m";
+const COLOR_PASSED: &str = "\x1b[42m";
+const COLOR_FAILED: &str = "\x1b[41m";
+const COLOR_SKIPPED: &str = "\x1b[43;30m";
+
+/// Status shown for a hook in the run summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStatus {
+    Passed,
+    Failed,
+    Skipped,
+    Modified,
+    TimedOut,
+}
+
+impl HookStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookStatus::Passed => "Passed",
+            HookStatus::Failed => "Failed",
+            HookStatus::Skipped => "Skipped",
+            HookStatus::Modified => "Modified",
+            HookStatus::TimedOut => "Timed out",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            HookStatus::Passed => COLOR_PASSED,
+            HookStatus::Skipped => COLOR_SKIPPED,
+            HookStatus::Failed | HookStatus::Modified | HookStatus::TimedOut => COLOR_FAILED,
+        }
+    }
+
+    pub fn is_failure(self) -> bool {
+        matches!(
+            self,
+            HookStatus::Failed | HookStatus::Modified | HookStatus::TimedOut
+        )
+    }
+}
+
+/// One line of the run summary.
+#[derive(Debug, Clone)]
+pub struct HookResult {
+    pub id: String,
+    pub name: String,
+    pub status: HookStatus,
+    /// Short explanation printed before the status, e.g. "(no files to check)".
+    pub note: Option<String>,
+    /// Details printed under the summary line when the hook did not pass.
+    pub details: Vec<String>,
+    /// Captured stdout and stderr of the hook's command.
+    pub output: String,
+    /// Show `output` even when the hook passed.
+    pub verbose: bool,
+}
+
+impl HookResult {
+    pub fn new(id: &str, name: &str, status: HookStatus) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            status,
+            note: None,
+            details: Vec::new(),
+            output: String::new(),
+            verbose: false,
+        }
+    }
+
+    /// Whether the captured output belongs under the summary line.
+    pub fn shows_output(&self) -> bool {
+        !self.output.is_empty() && (self.status.is_failure() || self.verbose)
+    }
+
+    /// Build a result from a hook's outcome.
+    pub fn from_outcome(id: &str, name: &str, outcome: HookOutcome) -> Self {
+        match outcome {
+            HookOutcome::Passed => Self::new(id, name, HookStatus::Passed),
+            HookOutcome::Skipped => {
+                let mut result = Self::new(id, name, HookStatus::Skipped);
+                result.note = Some("(no files to check)".to_string());
+                result
+            }
+            HookOutcome::Modified(files) => {
+                let mut result = Self::new(id, name, HookStatus::Modified);
+                result
+                    .details
+                    .push("files were modified by this hook".to_string());
+                result
+                    .details
+                    .extend(files.iter().map(|f| f.display().to_string()));
+                result
+            }
+            HookOutcome::Failed(messages) => {
+                let mut result = Self::new(id, name, HookStatus::Failed);
+                result.details = messages;
+                result
+            }
+            HookOutcome::TimedOut(limit) => {
+                let mut result = Self::new(id, name, HookStatus::TimedOut);
+                result
+                    .details
+                    .push(format!("killed after running for {:?}", limit));
+                result
+            }
+        }
+    }
+}
+
+/// Results of every hook that ran during `run-config`, in execution order.
+#[derive(Debug, Clone, Default)]
+pub struct RunSummary {
+    results: Vec<HookResult>,
+    errors: Vec<String>,
+}
+
+impl RunSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, result: HookResult) {
+        self.results.push(result);
+    }
+
+    /// Record a failure that does not belong to a single hook.
+    pub fn add_error(&mut self, message: &str) {
+        self.errors.push(message.to_string());
+    }
+
+    pub fn results(&self) -> &[HookResult] {
+        &self.results
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty() && !self.results.iter().any(|r| r.status.is_failure())
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.is_success() {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Render the summary table, one dotted line per hook.
+    pub fn render(&self, color: bool) -> String {
+        let mut out = String::new();
+        for result in &self.results {
+            let status = result.status.as_str();
+            let note = result.note.as_deref().unwrap_or("");
+            let used = result.name.chars().count() + note.chars().count() + status.len();
+            let dots = ".".repeat(LINE_WIDTH.saturating_sub(used).max(1));
+            let status = if color {
+                format!("{}{}{}", result.status.color(), status, COLOR_RESET)
+            } else {
+                status.to_string()
+            };
+            let _ = writeln!(out, "{}{}{}{}", result.name, dots, note, status);
+
+            let shows_output = result.shows_output();
+            if result.status.is_failure() || shows_output {
+                let _ = writeln!(out, "- hook id: {}", result.id);
+                for detail in &result.details {
+                    let _ = writeln!(out, "- {}", detail);
+                }
+                if shows_output {
+                    out.push('\n');
+                    out.push_str(&result.output);
+                    if !result.output.ends_with('\n') {
+                        out.push('\n');
+                    }
+                }
+                if !result.details.is_empty() || shows_output {
+                    out.push('\n');
+                }
+            }
+        }
+        for error in &self.errors {
+            let _ = writeln!(out, "{}", error);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn renders_padded_lines() {
+        let mut summary = RunSummary::new();
+        summary.push(HookResult::new("a", "check a", HookStatus::Passed));
+        summary.push(HookResult::from_outcome(
+            "b",
+            "check b",
+            HookOutcome::Skipped,
+        ));
+        let rendered = summary.render(false);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("check a..."));
+        assert!(lines[0].ends_with("...Passed"));
+        assert!(lines[1].ends_with("...(no files to check)Skipped"));
+        assert!(lines.iter().all(|l| l.len() == LINE_WIDTH));
+        assert!(summary.is_success());
+    }
+
+    #[test]
+    fn modified_and_failed_hooks_fail_the_run() {
+        let mut summary = RunSummary::new();
+        summary.push(HookResult::from_outcome(
+            "fix",
+            "fixer",
+            HookOutcome::Modified(vec![PathBuf::from("a.txt")]),
+        ));
+        assert!(!summary.is_success());
+        let rendered = summary.render(false);
+        assert!(rendered.contains("- hook id: fix"));
+        assert!(rendered.contains("- files were modified by this hook"));
+
+        let mut summary = RunSummary::new();
+        summary.push(HookResult::from_outcome(
+            "check",
+            "checker",
+            HookOutcome::Failed(vec!["bad".to_string()]),
+        ));
+        assert_eq!(summary.exit_code(), 1);
+
+        let mut summary = RunSummary::new();
+        summary.push(HookResult::from_outcome(
+            "slow",
+            "slow check",
+            HookOutcome::TimedOut(std::time::Duration::from_secs(5)),
+        ));
+        assert!(!summary.is_success());
+        let rendered = summary.render(false);
+        assert!(rendered.lines().next().unwrap().ends_with("...Timed out"));
+        assert!(rendered.contains("- killed after running for 5s"));
+    }
+
+    #[test]
+    fn output_is_shown_for_failures_and_verbose_hooks() {
+        let mut failed = HookResult::new("lint", "lint", HookStatus::Failed);
+        failed.output = "src/a.py:1: error".to_string();
+        let mut quiet = HookResult::new("fmt", "fmt", HookStatus::Passed);
+        quiet.output = "formatted 0 files".to_string();
+        let mut verbose = HookResult::new("count", "count", HookStatus::Passed);
+        verbose.output = "42 files\n".to_string();
+        verbose.verbose = true;
+
+        let mut summary = RunSummary::new();
+        summary.push(failed);
+        summary.push(quiet);
+        summary.push(verbose);
+        let rendered = summary.render(false);
+        assert!(rendered.contains("- hook id: lint\n\nsrc/a.py:1: error\n\n"));
+        assert!(!rendered.contains("formatted 0 files"));
+        assert!(rendered.contains("- hook id: count\n\n42 files\n"));
+    }
+}
@@ -1,18 +1,16 @@
+use crate::hooks::{Hook, HookKind, HookOutcome};
 use anyhow::{anyhow, Result};
 use std::fs;
 use std::path::PathBuf;
 use tempfile::tempdir;
 
-/// Identifies if a hook is a validator only (doesn't modify files, just checks them)
-fn is_validator_hook(hook_name: &str) -> bool {
-    matches!(hook_name, "check-yaml" | "check-added-large-files")
-}
-
 /// Test that a hook implementation meets the required contract
-pub fn validate_hook<F>(hook_name: &str, hook_fn: F) -> Result<()>
-where
-    F: Fn(&crate::RunContext, Vec<PathBuf>) -> Result<()>,
-{
+pub fn validate_hook(hook: &dyn Hook) -> Result<()> {
+    let hook_name = hook.id();
+    let hook_fn = |ctx: &crate::RunContext, paths: Vec<PathBuf>| -> Result<HookOutcome> {
+        hook.run(ctx, &[], paths)
+    };
+
     // Test 1: Basic hook call with no files
     let ctx = crate::RunContext::default();
     hook_fn(&ctx, vec![])?;
@@ -33,8 +31,8 @@ where
     hook_fn(&ctx, vec![test_file.clone()])?;
     let after_content = fs::read_to_string(&test_file)?;
 
-    // Only check for unmodified content if hook is not a validator
-    if !is_validator_hook(hook_name) && original_content != after_content {
+    // Only check for unmodified content if hook is a fixer
+    if hook.kind() == HookKind::Fixer && original_content != after_content {
         return Err(anyhow!("Hook {} modified file in dry-run mode", hook_name));
     }
 
@@ -50,33 +48,20 @@ where
     };
 
     // Test handling of a file that should trigger the hook
-    let bad_file = if hook_name == "check-yaml" {
-        // Create invalid YAML for check-yaml
-        let f = temp_dir.path().join("invalid.yaml");
-        fs::write(&f, "invalid: [yaml: }")?;
-        f
-    } else if hook_name == "check-added-large-files" {
-        // Create large file
-        let f = temp_dir.path().join("large.txt");
-        fs::write(&f, &vec![b'x'; 1_000_000])?;
-        f
-    } else {
-        // For fixer hooks, create file needing fixes
-        let f = temp_dir.path().join("needs-fixing.txt");
-        fs::write(&f, "test content")?; // No newline at end
-        f
-    };
+    let (sample_name, sample_content) = hook.failing_sample();
+    let bad_file = temp_dir.path().join(sample_name);
+    fs::write(&bad_file, sample_content)?;
 
     let would_fail = match hook_fn(&ctx, vec![bad_file.clone()]) {
-        Ok(_) => false,
+        Ok(outcome) => !outcome.is_success(),
         Err(_) => true,
     };
 
-    // For validator hooks, they should exit(1) on validation failures
-    // For fixer hooks, they should exit(1) on making changes
+    // Validator hooks must report Failed on validation failures and
+    // fixer hooks must report Modified when they change files
     if !would_fail {
         return Err(anyhow!(
-            "Hook {} did not indicate failure/changes via exit code when expected",
+            "Hook {} did not indicate failure/changes via its outcome when expected",
             hook_name
         ));
     }
@@ -99,3 +84,16 @@ where
     println!("✅ Hook {} passes all validation checks", hook_name);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::HookRegistry;
+
+    #[test]
+    fn builtin_hooks_pass_validation() {
+        for hook in HookRegistry::builtin().iter() {
+            validate_hook(hook).unwrap_or_else(|e| panic!("{}: {}", hook.id(), e));
+        }
+    }
+}
@@ -0,0 +1,585 @@
+use crate::compat;
+use crate::config::{self, InstallLanguage, PreCommitConfig};
+use crate::identify;
+use crate::pattern::{FileMatcher, PatternSyntax, Patterns};
+use anyhow::{Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+/// A mistake in a config file, at a 1-based line and column. Warnings
+/// point at something suspicious that does not stop the config from running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub warning: bool,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}{}",
+            self.path.display(),
+            self.line,
+            self.column,
+            if self.warning { "warning: " } else { "" },
+            self.message
+        )
+    }
+}
+
+/// One step on the way from the document root to a node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+fn key(name: &str) -> Step {
+    Step::Key(name.to_string())
+}
+
+enum Frame {
+    Mapping {
+        path: Vec<Step>,
+        key: Option<String>,
+    },
+    Sequence {
+        path: Vec<Step>,
+        next: usize,
+    },
+}
+
+/// Start of every key and value in a YAML document, by path. For mapping
+/// entries the position of the key is kept, which is where an editor
+/// should point for a bad value too.
+#[derive(Default)]
+struct Spans {
+    positions: HashMap<Vec<Step>, (usize, usize)>,
+    frames: Vec<Frame>,
+}
+
+impl Spans {
+    fn parse(content: &str) -> Self {
+        let mut spans = Spans::default();
+        // serde_yaml has already accepted the document; if this parser does
+        // not, problems are reported without a precise position
+        let _ = Parser::new_from_str(content).load(&mut spans, false);
+        spans.frames.clear();
+        spans
+    }
+
+    fn record(&mut self, path: Vec<Step>, mark: Marker) {
+        // yaml-rust2 columns are 0-based
+        self.positions
+            .entry(path)
+            .or_insert((mark.line(), mark.col() + 1));
+    }
+
+    /// Path of the value the next event starts, if it is not a mapping key.
+    fn value_path(&self) -> Option<Vec<Step>> {
+        match self.frames.last() {
+            None => Some(Vec::new()),
+            Some(Frame::Mapping { key: None, .. }) => None,
+            Some(Frame::Mapping {
+                path,
+                key: Some(key),
+            }) => {
+                let mut path = path.clone();
+                path.push(Step::Key(key.clone()));
+                Some(path)
+            }
+            Some(Frame::Sequence { path, next }) => {
+                let mut path = path.clone();
+                path.push(Step::Index(*next));
+                Some(path)
+            }
+        }
+    }
+
+    /// Move the innermost container past the value that just ended.
+    fn advance(&mut self) {
+        match self.frames.last_mut() {
+            Some(Frame::Mapping { key, .. }) => *key = None,
+            Some(Frame::Sequence { next, .. }) => *next += 1,
+            None => {}
+        }
+    }
+
+    fn start_container(&mut self, mark: Marker, mapping: bool) {
+        // A mapping or sequence used as a key, which configs never have,
+        // is filed under the root
+        let path = self.value_path().unwrap_or_default();
+        self.record(path.clone(), mark);
+        self.frames.push(if mapping {
+            Frame::Mapping { path, key: None }
+        } else {
+            Frame::Sequence { path, next: 0 }
+        });
+    }
+
+    /// Position of the node at `path`, or of its closest ancestor that was
+    /// recorded.
+    fn locate(&self, path: &[Step]) -> (usize, usize) {
+        (0..=path.len())
+            .rev()
+            .find_map(|len| self.positions.get(&path[..len]).copied())
+            .unwrap_or((1, 1))
+    }
+}
+
+impl MarkedEventReceiver for Spans {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::MappingStart(..) => self.start_container(mark, true),
+            Event::SequenceStart(..) => self.start_container(mark, false),
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.frames.pop();
+                self.advance();
+            }
+            Event::Scalar(value, ..) => match self.value_path() {
+                Some(path) => {
+                    self.record(path, mark);
+                    self.advance();
+                }
+                None => {
+                    if let Some(Frame::Mapping { path, key }) = self.frames.last_mut() {
+                        let mut key_path = path.clone();
+                        key_path.push(Step::Key(value.clone()));
+                        *key = Some(value);
+                        self.record(key_path, mark);
+                    }
+                }
+            },
+            Event::Alias(_) => {
+                if let Some(path) = self.value_path() {
+                    self.record(path, mark);
+                }
+                self.advance();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collects problems with their positions.
+struct Report<'a> {
+    path: &'a Path,
+    spans: Spans,
+    problems: Vec<Problem>,
+}
+
+impl Report<'_> {
+    fn add(&mut self, at: &[Step], message: String) {
+        self.push(at, message, false);
+    }
+
+    fn warn(&mut self, at: &[Step], message: String) {
+        self.push(at, message, true);
+    }
+
+    fn push(&mut self, at: &[Step], message: String, warning: bool) {
+        let (line, column) = self.spans.locate(at);
+        self.problems.push(Problem {
+            path: self.path.to_path_buf(),
+            line,
+            column,
+            message,
+            warning,
+        });
+    }
+}
+
+/// The known key closest to a misspelt `key`, if any is close enough to be
+/// what was meant.
+pub(crate) fn suggestion<'a>(key: &str, known: &'a [impl AsRef<str>]) -> Option<&'a str> {
+    let limit = (key.chars().count() / 3).max(1);
+    known
+        .iter()
+        .map(|candidate| candidate.as_ref())
+        .map(|candidate| (strsim::levenshtein(key, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn check_keys(
+    report: &mut Report,
+    mapping: &Mapping,
+    at: &[Step],
+    known: &[String],
+    upstream: fn(&str) -> bool,
+    context: &str,
+) {
+    for name in mapping.keys().filter_map(Value::as_str) {
+        if known.iter().any(|k| k == name) || upstream(name) {
+            continue;
+        }
+        let mut message = format!("unknown key '{}'{}", name, context);
+        if let Some(candidate) = suggestion(name, known) {
+            message.push_str(&format!(" (did you mean '{}'?)", candidate));
+        }
+        let mut path = at.to_vec();
+        path.push(key(name));
+        report.add(&path, message);
+    }
+}
+
+fn check_unknown_keys(report: &mut Report, doc: &Value) {
+    let Some(top) = doc.as_mapping() else {
+        return;
+    };
+    check_keys(
+        report,
+        top,
+        &[],
+        compat::config_keys(),
+        compat::is_upstream_config_key,
+        "",
+    );
+
+    let repos = top.get("repos").and_then(Value::as_sequence);
+    for (i, repo) in repos.into_iter().flatten().enumerate() {
+        let Some(repo) = repo.as_mapping() else {
+            continue;
+        };
+        let repo_path = vec![key("repos"), Step::Index(i)];
+        check_keys(
+            report,
+            repo,
+            &repo_path,
+            compat::repo_keys(),
+            |_| false,
+            " in repo",
+        );
+
+        let hooks = repo.get("hooks").and_then(Value::as_sequence);
+        for (j, hook) in hooks.into_iter().flatten().enumerate() {
+            let Some(hook) = hook.as_mapping() else {
+                continue;
+            };
+            let id = hook.get("id").and_then(Value::as_str).unwrap_or("");
+            let mut hook_path = repo_path.clone();
+            hook_path.extend([key("hooks"), Step::Index(j)]);
+            check_keys(
+                report,
+                hook,
+                &hook_path,
+                compat::hook_keys(),
+                compat::is_upstream_hook_key,
+                &format!(" in hook '{}'", id),
+            );
+
+            if let Some(install) = hook.get("install").and_then(Value::as_mapping) {
+                let mut install_path = hook_path.clone();
+                install_path.push(key("install"));
+                check_keys(
+                    report,
+                    install,
+                    &install_path,
+                    compat::install_keys(),
+                    |_| false,
+                    &format!(" in install of hook '{}'", id),
+                );
+            }
+        }
+    }
+}
+
+fn check_patterns(
+    report: &mut Report,
+    at: &[Step],
+    name: &str,
+    patterns: Option<&Patterns>,
+    syntax: PatternSyntax,
+) {
+    let Some(patterns) = patterns else {
+        return;
+    };
+    if let Err(e) = FileMatcher::new(patterns.as_slice(), syntax) {
+        let mut path = at.to_vec();
+        path.push(key(name));
+        report.add(&path, format!("invalid {}: {:#}", name, e));
+    }
+}
+
+fn check_hooks(report: &mut Report, cfg: &PreCommitConfig) {
+    check_patterns(report, &[], "files", cfg.files(), cfg.pattern_syntax());
+    check_patterns(report, &[], "exclude", cfg.exclude(), cfg.pattern_syntax());
+
+    // Upstream allows the same id twice, e.g. with different args
+    let upstream = compat::is_upstream_config(report.path);
+    let known_tags = identify::known_tags();
+    let mut first_seen: HashMap<&str, usize> = HashMap::new();
+    for (i, repo) in cfg.repos().iter().enumerate() {
+        for (j, hook) in repo.hooks().iter().enumerate() {
+            let at = vec![key("repos"), Step::Index(i), key("hooks"), Step::Index(j)];
+            let child = |name: &str| {
+                let mut path = at.clone();
+                path.push(key(name));
+                path
+            };
+
+            // Only local hooks run, so only they can clash
+            let (line, _) = report.spans.locate(&child("id"));
+            if repo.repo() == "local" {
+                if let Some(first) = first_seen.get(hook.id()) {
+                    let message = format!(
+                        "duplicate hook id '{}' (first defined on line {})",
+                        hook.id(),
+                        first
+                    );
+                    if upstream {
+                        report.warn(&child("id"), message);
+                    } else {
+                        report.add(&child("id"), message);
+                    }
+                } else {
+                    first_seen.insert(hook.id(), line);
+                }
+            }
+
+            let syntax = hook.pattern_syntax(cfg.pattern_syntax());
+            check_patterns(report, &at, "files", hook.files(), syntax);
+            check_patterns(report, &at, "exclude", hook.exclude(), syntax);
+
+            for (name, tags) in [
+                ("types", hook.types()),
+                ("types_or", hook.types_or()),
+                ("exclude_types", hook.exclude_types()),
+            ] {
+                for (k, tag) in tags.iter().enumerate() {
+                    if identify::is_known_tag(tag) {
+                        continue;
+                    }
+                    let mut path = child(name);
+                    path.push(Step::Index(k));
+                    // Only a near miss of a known tag is surely a mistake;
+                    // upstream knows many tags that are missing here
+                    match suggestion(tag, &known_tags) {
+                        Some(candidate) => report.add(
+                            &path,
+                            format!(
+                                "unknown file type '{}' in {} (did you mean '{}'?)",
+                                tag, name, candidate
+                            ),
+                        ),
+                        None => report.warn(
+                            &path,
+                            format!(
+                                "file type '{}' in {} is not known to precommit-rs and matches no file",
+                                tag, name
+                            ),
+                        ),
+                    }
+                }
+            }
+
+            let install = hook.install();
+            if hook.command_is_install() && install.is_none() {
+                report.add(
+                    &child("command"),
+                    format!(
+                        "hook '{}' uses command \"{{install}}\" but has no install block",
+                        hook.id()
+                    ),
+                );
+            }
+            if let Some(install) = install {
+                let target = install.package().or(install.repo()).unwrap_or("");
+                if matches!(install.language(), InstallLanguage::Go)
+                    && install.version().is_none()
+                    && !target.contains('@')
+                {
+                    report.add(
+                        &child("install"),
+                        format!(
+                            "Go install of hook '{}' needs a version: set install.version or add '@<version>' to package",
+                            hook.id()
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Check `content`, read from `path`, without running anything: unknown
+/// keys (with the closest known key as a suggestion), invalid `files` and
+/// `exclude` patterns, unknown file types, duplicate hook ids, `{install}`
+/// commands without an install block and Go installs without a version.
+/// Returns every problem found, in file order; the config can still run
+/// if all of them are warnings.
+pub fn validate(path: &Path, content: &str) -> Vec<Problem> {
+    check(path, content).0
+}
+
+/// `validate`, also returning the config when it could be loaded.
+fn check(path: &Path, content: &str) -> (Vec<Problem>, Option<PreCommitConfig>) {
+    let mut report = Report {
+        path,
+        spans: Spans::default(),
+        problems: Vec::new(),
+    };
+    let located = |e: serde_yaml::Error| {
+        let (position, message) = config::yaml_error_position(&e);
+        let (line, column) = position.unwrap_or((1, 1));
+        Problem {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message,
+            warning: false,
+        }
+    };
+
+    let doc: Value = match serde_yaml::from_str(content) {
+        Ok(doc) => doc,
+        Err(e) => return (vec![located(e)], None),
+    };
+    report.spans = Spans::parse(content);
+    check_unknown_keys(&mut report, &doc);
+
+    let cfg = match serde_yaml::from_str::<PreCommitConfig>(content) {
+        Ok(cfg) => {
+            let cfg = cfg.loaded_from(path, &doc);
+            check_hooks(&mut report, &cfg);
+            Some(cfg)
+        }
+        Err(e) => {
+            report.problems.push(located(e));
+            None
+        }
+    };
+
+    let mut problems = report.problems;
+    problems.sort_by_key(|p| (p.line, p.column));
+    (problems, cfg)
+}
+
+/// `validate` the config at `path`, or the config file in `path` when it is
+/// a directory. The config is returned too when it could be loaded, so a
+/// caller that goes on to run it reads and parses the file once.
+pub fn validate_file(path: &Path) -> Result<(Vec<Problem>, Option<PreCommitConfig>)> {
+    let path = config::resolve_config_path(path)?;
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(check(&path, &content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(content: &str) -> Vec<String> {
+        validate(Path::new(".pre-commit.yaml"), content)
+            .iter()
+            .map(|p| p.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn reports_typos_with_suggestions_and_positions() {
+        let found = problems(
+            "exlude: vendor/**
+repos:
+  - repo: local
+    hooks:
+      - id: check-yaml
+        enable: false
+        files: '**/*.{yaml'
+      - id: lint
+        command: \"{install}\"
+        types: [pyhton]
+      - id: check-yaml
+      - id: golangci
+        command: \"{install}\"
+        install:
+          language: go
+          package: github.com/golangci/golangci-lint/cmd/golangci-lint
+",
+        );
+        assert_eq!(
+            found,
+            vec![
+                ".pre-commit.yaml:1:1: unknown key 'exlude' (did you mean 'exclude'?)",
+                ".pre-commit.yaml:6:9: unknown key 'enable' in hook 'check-yaml' (did you mean 'enabled'?)",
+                ".pre-commit.yaml:7:9: invalid files: Unmatched '{' in pattern '**/*.{yaml'",
+                ".pre-commit.yaml:9:9: hook 'lint' uses command \"{install}\" but has no install block",
+                ".pre-commit.yaml:10:17: unknown file type 'pyhton' in types (did you mean 'python'?)",
+                ".pre-commit.yaml:11:9: duplicate hook id 'check-yaml' (first defined on line 5)",
+                ".pre-commit.yaml:14:9: Go install of hook 'golangci' needs a version: set install.version or add '@<version>' to package",
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_valid_configs_and_upstream_keys() {
+        assert!(problems(
+            "default_language_version:
+  python: python3
+repos:
+  - repo: local
+    hooks:
+      - id: check-yaml
+        alias: yaml
+        files: '**/*.yaml'
+"
+        )
+        .is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".pre-commit.yaml");
+        config::write_default_config(&path).unwrap();
+        let (problems, cfg) = validate_file(dir.path()).unwrap();
+        assert_eq!(problems, vec![]);
+        assert_eq!(cfg.unwrap().path(), path);
+    }
+
+    #[test]
+    fn unknown_types_and_upstream_duplicates_are_warnings() {
+        let content = "repos:
+  - repo: https://github.com/psf/black
+    rev: 24.1.0
+    hooks:
+      - id: black
+      - id: black
+  - repo: local
+    hooks:
+      - id: nbstripout
+        entry: nbstripout
+        types: [jupyter]
+      - id: nbstripout
+        entry: nbstripout --keep-count
+        types: [jupyter]
+";
+        let found: Vec<String> = validate(Path::new(".pre-commit-config.yaml"), content)
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ".pre-commit-config.yaml:11:17: warning: file type 'jupyter' in types is not known to precommit-rs and matches no file",
+                ".pre-commit-config.yaml:12:9: warning: duplicate hook id 'nbstripout' (first defined on line 9)",
+                ".pre-commit-config.yaml:14:17: warning: file type 'jupyter' in types is not known to precommit-rs and matches no file",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_yaml_and_type_errors_at_their_position() {
+        assert_eq!(
+            problems("repos:\n  - repo: local\n    hooks:\n      - id: x\n        enabled: maybe\n"),
+            vec![".pre-commit.yaml:5:18: repos[0].hooks[0].enabled: invalid type: string \"maybe\", expected a boolean"]
+        );
+        assert_eq!(problems("repos: [\n").len(), 1);
+    }
+}